@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{QueryType, Record};
+
+struct Entry {
+    records: Vec<Record>,
+    expires: Instant,
+    last_used: u64,
+}
+
+/// A cache of resolved records, keyed by name and query type.
+///
+/// Entries expire after the smallest TTL among their records.  Once `max_entries` entries are
+/// cached, inserting a new one evicts the least recently used.
+pub struct Cache {
+    entries: HashMap<(String, QueryType), Entry>,
+    max_entries: usize,
+    clock: u64,
+}
+
+impl Cache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_entries,
+            clock: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Look up unexpired records for `name`, marking the entry as recently used.
+    pub fn get(&mut self, name: &str, ty: QueryType) -> Option<&[Record]> {
+        let key = (name.to_ascii_lowercase(), ty);
+        if self
+            .entries
+            .get(&key)
+            .is_some_and(|entry| entry.expires <= Instant::now())
+        {
+            self.entries.remove(&key);
+            return None;
+        }
+        let now = self.tick();
+        let entry = self.entries.get_mut(&key)?;
+        entry.last_used = now;
+        Some(&entry.records)
+    }
+
+    /// Cache `records` as the answer for `name`, evicting the least recently used entry if the
+    /// cache is full.
+    pub fn insert(&mut self, name: &str, ty: QueryType, records: Vec<Record>) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let key = (name.to_ascii_lowercase(), ty);
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.max_entries {
+            let now = Instant::now();
+            self.entries.retain(|_, entry| entry.expires > now);
+            if self.entries.len() >= self.max_entries {
+                let lru = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(lru) = lru {
+                    self.entries.remove(&lru);
+                }
+            }
+        }
+        let ttl = records.iter().map(|record| record.ttl).min().unwrap_or(0);
+        let last_used = self.tick();
+        self.entries.insert(
+            key,
+            Entry {
+                records,
+                expires: Instant::now() + Duration::from_secs(ttl.into()),
+                last_used,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::QueryResponse;
+    use std::net::Ipv4Addr;
+
+    fn record(name: &str) -> Record {
+        Record {
+            name: name.into(),
+            ty: QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+            ttl: 300,
+            data: vec![192, 0, 2, 1],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = Cache::new(2);
+        cache.insert("a.example", QueryType::A, vec![record("a.example")]);
+        cache.insert("b.example", QueryType::A, vec![record("b.example")]);
+
+        // touching a.example makes b.example the least recently used
+        assert!(cache.get("a.example", QueryType::A).is_some());
+        cache.insert("c.example", QueryType::A, vec![record("c.example")]);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b.example", QueryType::A).is_none());
+        assert!(cache.get("a.example", QueryType::A).is_some());
+        assert!(cache.get("c.example", QueryType::A).is_some());
+    }
+
+    #[test]
+    fn test_expired_entries() {
+        let mut cache = Cache::new(2);
+        let mut expired = record("a.example");
+        expired.ttl = 0;
+        cache.insert("a.example", QueryType::A, vec![expired]);
+
+        assert!(cache.get("a.example", QueryType::A).is_none());
+        assert!(cache.is_empty());
+    }
+}
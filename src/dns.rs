@@ -1,14 +1,28 @@
 use std::{
-    io::Write,
-    net::{Ipv4Addr, Ipv6Addr},
+    collections::HashSet,
+    fmt::Display,
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::Path,
 };
 
+mod edns;
+mod query_names;
+#[cfg(feature = "extensible")]
+mod registry;
 mod types;
+use crate::{read_tcp_message, DnsError, ParseError};
+use base64::prelude::*;
+use clap::ValueEnum;
 use color_eyre::eyre::Context;
+pub use edns::*;
+pub use query_names::*;
+#[cfg(feature = "extensible")]
+pub use registry::RdataParser;
 pub use types::*;
 use winnow::{
     binary::{be_u16, be_u32, u8},
-    combinator::repeat,
+    combinator::{repeat, rest},
     error::{ErrMode, Error, ErrorKind},
     multi::length_data,
     token::take,
@@ -23,6 +37,7 @@ pub trait AsBytes {
 
 /// A DNS Header.  Can be converted to wire format using the `AsBytes` trait impl.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Header {
     id: u16,
     flags: u16,
@@ -45,6 +60,129 @@ impl Header {
             })
             .parse_next(input)
     }
+
+    /// A header with the given `id` and `flags` word, and every count zero.  Set the counts to
+    /// match the sections that follow it before encoding a message.
+    pub fn new(id: u16, flags: u16) -> Self {
+        Self {
+            id,
+            flags,
+            ..Default::default()
+        }
+    }
+
+    /// Set the id the response must echo from its query
+    pub fn set_id(&mut self, id: u16) {
+        self.id = id;
+    }
+
+    /// Set the raw 16-bit flags word
+    pub fn set_flags(&mut self, flags: u16) {
+        self.flags = flags;
+    }
+
+    /// Set the number of questions the message claims to hold
+    pub fn set_num_questions(&mut self, count: u16) {
+        self.num_questions = count;
+    }
+
+    /// Set the number of answer records the message claims to hold
+    pub fn set_num_answers(&mut self, count: u16) {
+        self.num_answers = count;
+    }
+
+    /// Set the number of authority records the message claims to hold
+    pub fn set_num_authorities(&mut self, count: u16) {
+        self.num_authorities = count;
+    }
+
+    /// Set the number of additional records the message claims to hold
+    pub fn set_num_additionals(&mut self, count: u16) {
+        self.num_additionals = count;
+    }
+
+    /// The id the response must echo from its query
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// The raw 16-bit flags word
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// Whether the QR bit is set, marking this message as a response
+    pub fn response(&self) -> bool {
+        self.flags & FLAG_QR != 0
+    }
+
+    /// Whether the AA bit is set
+    pub fn authoritative(&self) -> bool {
+        self.flags & FLAG_AA != 0
+    }
+
+    /// Whether the TC bit is set
+    pub fn truncated(&self) -> bool {
+        self.flags & FLAG_TC != 0
+    }
+
+    /// Whether the RD bit is set
+    pub fn recursion_desired(&self) -> bool {
+        self.flags & FLAG_RD != 0
+    }
+
+    /// Whether the RA bit is set
+    pub fn recursion_available(&self) -> bool {
+        self.flags & FLAG_RA != 0
+    }
+
+    /// The 3-bit Z field
+    pub fn z(&self) -> u8 {
+        ((self.flags & Z_MASK) >> 4) as u8
+    }
+
+    /// The bits of the Z field no later RFC has assigned, which should be zero.  AD and CD, which
+    /// RFC 4035 carved out of the field, are left out, so DNSSEC-aware servers don't trip this.
+    pub fn reserved_bits(&self) -> u8 {
+        ((self.flags & FLAG_Z) >> 4) as u8
+    }
+
+    /// The 4-bit RCODE field
+    pub fn rcode(&self) -> u8 {
+        (self.flags & RCODE_MASK) as u8
+    }
+
+    /// The outcome of the query, from the RCODE field.  This misses the upper bits of extended
+    /// response codes, which are carried in the OPT record; `Response::response_code` includes
+    /// them.
+    pub fn response_code(&self) -> ResponseCode {
+        ResponseCode::from(self.rcode())
+    }
+
+    /// The kind of message this is
+    pub fn opcode(&self) -> Opcode {
+        Opcode::from(((self.flags & OPCODE_MASK) >> OPCODE_SHIFT) as u8)
+    }
+
+    /// The number of questions the message claims to hold
+    pub fn num_questions(&self) -> u16 {
+        self.num_questions
+    }
+
+    /// The number of answer records the message claims to hold
+    pub fn num_answers(&self) -> u16 {
+        self.num_answers
+    }
+
+    /// The number of authority records the message claims to hold
+    pub fn num_authorities(&self) -> u16 {
+        self.num_authorities
+    }
+
+    /// The number of additional records the message claims to hold
+    pub fn num_additionals(&self) -> u16 {
+        self.num_additionals
+    }
 }
 
 impl AsBytes for Header {
@@ -64,6 +202,7 @@ impl AsBytes for Header {
 
 /// A DNS Question.  Can be converted to wire format using the `AsBytes` trait impl.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Question {
     name: String,
     ty: QueryType,
@@ -79,6 +218,18 @@ impl Question {
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
+    pub fn class(&self) -> ClassType {
+        self.class
+    }
+
     fn parse<'a, 'b>(input: &'a [u8], full_input: &'b [u8]) -> IResult<&'a [u8], Self>
     where
         'b: 'a,
@@ -99,6 +250,33 @@ impl Question {
 
 const MAX_PTR_TRAVERSALS: u8 = 126;
 
+/// The offset of `bytes` within `full_input`, or the length of `full_input` if `bytes` isn't a
+/// part of it
+fn offset_in(bytes: &[u8], full_input: &[u8]) -> usize {
+    (bytes.as_ptr() as usize)
+        .checked_sub(full_input.as_ptr() as usize)
+        .filter(|&offset| offset <= full_input.len())
+        .unwrap_or(full_input.len())
+}
+
+/// Render a label in presentation format ([RFC 1035 section
+/// 5.1](https://datatracker.ietf.org/doc/html/rfc1035#section-5.1)), escaping special
+/// characters as `\X` and bytes that aren't printable ASCII as `\DDD`.
+fn escape_label(label: &[u8]) -> String {
+    let mut output = String::with_capacity(label.len());
+    for &byte in label {
+        match byte {
+            b'.' | b'\\' | b'"' | b'(' | b')' | b';' | b'@' | b'$' => {
+                output.push('\\');
+                output.push(byte as char);
+            }
+            0x21..=0x7e => output.push(byte as char),
+            _ => output.push_str(&format!("\\{byte:03}")),
+        }
+    }
+    output
+}
+
 fn decode_helper<'a, 'b>(
     bytes: &'a [u8],
     full_input: &'b [u8],
@@ -108,32 +286,42 @@ where
     'b: 'a,
 {
     if depth > MAX_PTR_TRAVERSALS {
-        return Err(ErrMode::Cut(Error::new(bytes, ErrorKind::Verify)))
+        return Err(ErrMode::Cut(Error::new(bytes, ErrorKind::Verify)));
     }
     let (remaining, head) = u8.parse_next(bytes)?;
     if head & 0b1100_0000 == 0b11000000 {
         // pointer
         let (remaining, next) = u8.parse_next(remaining)?;
         let index = ((((head & 0b0011_1111) as u16) << 8) | (next as u16)) as usize;
-        if index > full_input.len() {
-            return Err(ErrMode::Cut(Error::new(full_input, ErrorKind::Fail)));
+        if index >= full_input.len() {
+            // the pointer refers past the end of the message, so report it as running out of
+            // input at the pointer itself
+            return Err(ErrMode::Cut(Error::new(&bytes[..2], ErrorKind::Eof)));
+        }
+        // pointers may only refer to names earlier in the message
+        if index >= offset_in(bytes, full_input) {
+            return Err(ErrMode::Cut(Error::new(&bytes[..2], ErrorKind::Verify)));
         }
         let (_, output) = decode_helper(&full_input[index..], full_input, depth + 1)?;
         Ok((remaining, output))
+    } else if head & 0b1100_0000 != 0 {
+        // 0b01 and 0b10 are reserved label types (RFC 1035 section 4.1.4, RFC 6891 section 5),
+        // not lengths: labels are at most 63 bytes long
+        Err(ErrMode::Cut(Error::new(&bytes[..1], ErrorKind::Verify)))
     } else if head == 0 {
         // end of input
         Ok((remaining, "".into()))
     } else {
         // sequence of labels
         let (remaining, x) = take(head as usize)
-            .map(String::from_utf8_lossy)
+            .map(escape_label)
             .parse_next(remaining)?;
         let (remaining, other) = decode_helper(remaining, full_input, depth + 1)?;
         if !other.is_empty() {
             let output = format!("{x}.{other}");
             Ok((remaining, output))
         } else {
-            Ok((remaining, x.into()))
+            Ok((remaining, x))
         }
     }
 }
@@ -145,11 +333,265 @@ where
     decode_helper(bytes, full_input, 0)
 }
 
+/// Whether `a` and `b` are the same name.  Names are compared case-insensitively ([RFC
+/// 4343](https://datatracker.ietf.org/doc/html/rfc4343)), ignoring any trailing dot.
+pub fn names_equal(a: &str, b: &str) -> bool {
+    a.trim_end_matches('.')
+        .eq_ignore_ascii_case(b.trim_end_matches('.'))
+}
+
+/// Whether `name` is `zone` or one of its subdomains.  An empty `zone` is the root.
+pub fn in_bailiwick(name: &str, zone: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    let zone = zone.trim_end_matches('.');
+    if zone.is_empty() || name.eq_ignore_ascii_case(zone) {
+        return true;
+    }
+    name.len() > zone.len()
+        && name.as_bytes()[name.len() - zone.len() - 1] == b'.'
+        && name[name.len() - zone.len()..].eq_ignore_ascii_case(zone)
+}
+
+/// Parse the entirety of a record's rdata with `parser`
+fn parse_rdata<'a, O>(
+    rdata: &'a [u8],
+    mut parser: impl Parser<&'a [u8], O, Error<&'a [u8]>>,
+) -> color_eyre::Result<O> {
+    parser
+        .parse(rdata)
+        .map_err(|e| color_eyre::eyre::eyre!("Got error from winnow: {e:?}"))
+}
+
+/// Parse the rdata of a record of a type this crate doesn't know, with the parser registered for
+/// `ty` if there is one.  `message` is the entire message the record came from.
+#[cfg_attr(not(feature = "extensible"), allow(unused_variables))]
+fn parse_unknown(ty: u16, rdata: &[u8], message: &[u8]) -> color_eyre::Result<QueryResponse> {
+    #[cfg(feature = "extensible")]
+    if let Some(parser) = registry::registered_parser(ty) {
+        let data = parser(rdata, message).context(format!("Failed to parse type {ty} record"))?;
+        return Ok(QueryResponse::Custom { ty, data });
+    }
+    Ok(QueryResponse::Unknown {
+        ty,
+        data: rdata.to_owned(),
+    })
+}
+
+impl Ds {
+    fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+        (be_u16, u8, u8, rest)
+            .map(|x: (u16, u8, u8, &[u8])| Ds {
+                key_tag: x.0,
+                algorithm: x.1,
+                digest_type: x.2,
+                digest: x.3.to_vec(),
+            })
+            .parse_next(input)
+    }
+}
+
+impl Display for Ds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.key_tag,
+            self.algorithm,
+            self.digest_type,
+            to_hex(&self.digest).to_uppercase()
+        )
+    }
+}
+
+impl Display for Soa {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {} {}",
+            self.mname,
+            self.rname,
+            self.serial,
+            self.refresh,
+            self.retry,
+            self.expire,
+            self.minimum
+        )
+    }
+}
+
+impl Rrsig {
+    fn parse<'a, 'b>(input: &'a [u8], full_input: &'b [u8]) -> IResult<&'a [u8], Self>
+    where
+        'b: 'a,
+    {
+        (
+            be_u16.map(|ty| QueryType::try_from(ty).unwrap_or(QueryType::Unknown(ty))),
+            u8,
+            u8,
+            be_u32,
+            be_u32,
+            be_u32,
+            be_u16,
+            |x| -> IResult<&'a [u8], String> { decode_dns_name(x, full_input) },
+            rest,
+        )
+            .map(|x| Rrsig {
+                type_covered: x.0,
+                algorithm: x.1,
+                labels: x.2,
+                original_ttl: x.3,
+                expiration: x.4,
+                inception: x.5,
+                key_tag: x.6,
+                signer_name: x.7,
+                signature: x.8.to_vec(),
+            })
+            .parse_next(input)
+    }
+}
+
+impl Display for Rrsig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let type_covered = self
+            .type_covered
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_else(|| format!("TYPE{}", u16::from(self.type_covered)));
+        write!(
+            f,
+            "{} {} {} {} {} {} {} {} {}",
+            type_covered,
+            self.algorithm,
+            self.labels,
+            self.original_ttl,
+            signature_time(self.expiration),
+            signature_time(self.inception),
+            self.key_tag,
+            self.signer_name,
+            BASE64_STANDARD.encode(&self.signature)
+        )
+    }
+}
+
+/// Render a signature expiration or inception time as `YYYYMMDDHHmmSS` in UTC, as described in
+/// [RFC 4034 section 3.2](https://datatracker.ietf.org/doc/html/rfc4034#section-3.2)
+fn signature_time(timestamp: u32) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+    // convert days since the epoch to a civil date; see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}{:02}{:02}{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+impl Dnskey {
+    fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+        (be_u16, u8, u8, rest)
+            .map(|x: (u16, u8, u8, &[u8])| Dnskey {
+                flags: x.0,
+                protocol: x.1,
+                algorithm: x.2,
+                public_key: x.3.to_vec(),
+            })
+            .parse_next(input)
+    }
+}
+
+impl Display for Dnskey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.flags,
+            self.protocol,
+            self.algorithm,
+            BASE64_STANDARD.encode(&self.public_key)
+        )
+    }
+}
+
+/// Parse the length-prefixed rdata of a record.  Fails with `ErrorKind::Complete` at the length
+/// if it claims more bytes than are left, so `Response::parse` can report it.
+fn rdata(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (remaining, claimed) = be_u16(input)?;
+    if remaining.len() < claimed as usize {
+        return Err(ErrMode::Cut(Error::new(input, ErrorKind::Complete)));
+    }
+    take(claimed).parse_next(remaining)
+}
+
+/// Parse a length-prefixed character-string
+fn character_string(input: &[u8]) -> IResult<&[u8], String> {
+    length_data(u8)
+        .map(|x| String::from_utf8_lossy(x).to_string())
+        .parse_next(input)
+}
+
+/// Render a character-string in double quotes, as it would appear in a zone file
+fn quote(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Render `bytes` as lowercase hexadecimal
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Encode a name given in presentation format, undoing any escapes in its labels
 pub fn encode_dns_name(name: &str) -> Vec<u8> {
+    let bytes = name.as_bytes();
+    let mut labels = vec![vec![]];
+    let mut i = 0;
+    while i < bytes.len() {
+        let label = labels.last_mut().unwrap();
+        match bytes[i] {
+            b'.' => labels.push(vec![]),
+            b'\\' => {
+                let escaped = bytes
+                    .get(i + 1..i + 4)
+                    .and_then(|digits| std::str::from_utf8(digits).ok())
+                    .filter(|digits| digits.bytes().all(|d| d.is_ascii_digit()))
+                    .and_then(|digits| digits.parse::<u8>().ok());
+                match (escaped, bytes.get(i + 1)) {
+                    (Some(byte), _) => {
+                        label.push(byte);
+                        i += 3;
+                    }
+                    (None, Some(&byte)) => {
+                        label.push(byte);
+                        i += 1;
+                    }
+                    (None, None) => label.push(b'\\'),
+                }
+            }
+            byte => label.push(byte),
+        }
+        i += 1;
+    }
+
+    // the root, and fully-qualified names ending in a dot
+    if labels.last().is_some_and(|label| label.is_empty()) {
+        labels.pop();
+    }
+
     let mut output = vec![];
-    for substr in name.split('.') {
-        output.push(substr.len() as u8);
-        let _ = output.write_all(substr.as_bytes());
+    for label in labels {
+        output.push(label.len() as u8);
+        let _ = output.write_all(&label);
     }
     output.push(0u8);
     output
@@ -161,49 +603,293 @@ impl AsBytes for Question {
         T: std::io::Write,
     {
         let _ = dest.write_all(&encode_dns_name(&self.name));
-        let _ = dest.write_all(&(self.ty as u16).to_be_bytes());
+        let _ = dest.write_all(&u16::from(self.ty).to_be_bytes());
         let _ = dest.write_all(&(self.class as u16).to_be_bytes());
     }
 }
 
+/// Query/response: set on responses
+const FLAG_QR: u16 = 0x8000;
+
+/// Authoritative answer: the responding server is an authority for the queried name
+const FLAG_AA: u16 = 0x0400;
+
+/// Truncated: the message was cut short to fit the transport
+const FLAG_TC: u16 = 0x0200;
+
+/// The OPCODE field of the flags word
+const OPCODE_MASK: u16 = 0x7800;
+const OPCODE_SHIFT: u16 = 11;
+
+/// Recursion desired: ask the server to resolve the query on our behalf
+const FLAG_RD: u16 = 0x0100;
+
+/// Recursion available: the responding server offers recursive resolution
+const FLAG_RA: u16 = 0x0080;
+
+/// The Z field, reserved in RFC 1035 and since home to the AD and CD bits
+const Z_MASK: u16 = 0x0070;
+
+/// The one bit of the Z field that's still reserved ([RFC 6895 section
+/// 2](https://datatracker.ietf.org/doc/html/rfc6895#section-2))
+const FLAG_Z: u16 = 0x0040;
+
+/// The RCODE field
+const RCODE_MASK: u16 = 0x000f;
+
+/// Authentic data: the responding server validated the answer with DNSSEC
+const FLAG_AD: u16 = 0x0020;
+
+/// Checking disabled: ask a validating server to return data even if it fails DNSSEC validation
+const FLAG_CD: u16 = 0x0010;
+
+/// DNSSEC OK: the high bit of the flags in an OPT record's TTL ([RFC 3225 section
+/// 3](https://datatracker.ietf.org/doc/html/rfc3225#section-3))
+const EDNS_FLAG_DO: u32 = 0x0000_8000;
+
+/// UDP payload size advertised when EDNS is needed but no size was chosen
+pub const DEFAULT_EDNS_PAYLOAD_SIZE: u16 = 1232;
+
+/// The smallest a question can be on the wire: a root name, type, and class
+const MIN_QUESTION_SIZE: usize = 5;
+
+/// The smallest a record can be on the wire: a root name, type, class, TTL, and rdata length
+const MIN_RECORD_SIZE: usize = 11;
+
+/// Encodes outgoing queries.  The builder holds everything about a query except the name, type,
+/// and id, so one builder can be reused for many queries.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct QueryBuilder {
+    flags: u16,
+    payload_size: Option<u16>,
+    dnssec_ok: bool,
+    class: ClassType,
+    cookie: Option<EdnsOption>,
+}
+
+impl QueryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the raw 16-bit header flags word, overwriting any bits set by the other methods.
+    /// Useful for experimenting with reserved bits or unusual combinations.
+    pub fn flags(mut self, flags: u16) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the OPCODE, to send a message other than a standard query.  Only the low 4 bits of
+    /// an `Opcode::Other` are used.
+    pub fn opcode(mut self, opcode: Opcode) -> Self {
+        let opcode = (u8::from(opcode) as u16) << OPCODE_SHIFT;
+        self.flags = (self.flags & !OPCODE_MASK) | (opcode & OPCODE_MASK);
+        self
+    }
+
+    /// Set the RD (recursion desired) bit.
+    pub fn recursion_desired(mut self, recursion_desired: bool) -> Self {
+        if recursion_desired {
+            self.flags |= FLAG_RD;
+        } else {
+            self.flags &= !FLAG_RD;
+        }
+        self
+    }
+
+    /// Set the CD (checking disabled) bit.
+    pub fn checking_disabled(mut self, checking_disabled: bool) -> Self {
+        if checking_disabled {
+            self.flags |= FLAG_CD;
+        } else {
+            self.flags &= !FLAG_CD;
+        }
+        self
+    }
+
+    /// Send an OPT record advertising `payload_size` as the largest UDP response we accept.
+    pub fn edns(mut self, payload_size: u16) -> Self {
+        self.payload_size = Some(payload_size);
+        self
+    }
+
+    /// Set the DO (DNSSEC OK) bit in the OPT record, asking for DNSSEC records to be included
+    /// in the response.  Enables EDNS if it isn't already.
+    pub fn dnssec_ok(mut self, dnssec_ok: bool) -> Self {
+        self.dnssec_ok = dnssec_ok;
+        if dnssec_ok && self.payload_size.is_none() {
+            self.payload_size = Some(DEFAULT_EDNS_PAYLOAD_SIZE);
+        }
+        self
+    }
+
+    /// Send a DNS cookie ([RFC 7873](https://datatracker.ietf.org/doc/html/rfc7873)) made of the
+    /// client cookie `client` and the server cookie `server`, which should be the one the server
+    /// last sent (see `Response::server_cookie`), or empty before it has sent one.  Enables EDNS
+    /// if it isn't already.
+    pub fn cookie(mut self, client: [u8; 8], server: &[u8]) -> Self {
+        self.cookie = Some(EdnsOption::Cookie {
+            client,
+            server: server.to_vec(),
+        });
+        if self.payload_size.is_none() {
+            self.payload_size = Some(DEFAULT_EDNS_PAYLOAD_SIZE);
+        }
+        self
+    }
+
+    /// The client cookie sent with built queries, which responses must echo
+    pub fn client_cookie(&self) -> Option<[u8; 8]> {
+        match self.cookie {
+            Some(EdnsOption::Cookie { client, .. }) => Some(client),
+            _ => None,
+        }
+    }
+
+    /// Set the class of the question, e.g. `ClassType::CH` for queries like `version.bind`.
+    /// Defaults to `ClassType::IN`.
+    pub fn class(mut self, class: ClassType) -> Self {
+        self.class = class;
+        self
+    }
+
+    /// The class of the question in built queries
+    pub fn query_class(&self) -> ClassType {
+        self.class
+    }
+
+    /// The UDP payload size advertised in the OPT record, if one is sent
+    pub fn payload_size(&self) -> Option<u16> {
+        self.payload_size
+    }
+
+    /// Encode a query for `domain_name` with the message id `id`.  The bytes depend only on the
+    /// builder and the arguments, so a fixed id gives the same query every time.  RD is clear
+    /// unless set with `recursion_desired`, as an iterative resolver wants.
+    pub fn build(&self, domain_name: &str, record_type: QueryType, id: u16) -> Vec<u8> {
+        let mut output = vec![];
+        let header = Header {
+            id,
+            flags: self.flags,
+            num_questions: 1,
+            num_additionals: self.payload_size.is_some().into(),
+            ..Default::default()
+        };
+        let question = Question::new(domain_name, record_type, self.class);
+        header.as_bytes(&mut output);
+        question.as_bytes(&mut output);
+        if let Some(payload_size) = self.payload_size {
+            let opt = QueryResponse::Opt {
+                payload_size,
+                options: self.cookie.iter().cloned().collect(),
+            };
+            let ttl = if self.dnssec_ok { EDNS_FLAG_DO } else { 0 };
+            Record::new("", opt, ClassType::default(), ttl).as_bytes(&mut output);
+        }
+        output
+    }
+}
+
+/// Parse a response from untrusted input, such as a fuzzer's.  Never panics: input that isn't a
+/// valid message, however malformed, is reported as a `ParseError`.
+pub fn parse_bytes(input: &[u8]) -> Result<Response, ParseError> {
+    Ok(Response::parse(input)?)
+}
+
 pub fn build_query(domain_name: &str, record_type: QueryType, id: u16) -> Vec<u8> {
-    let mut output = vec![];
-    let header = Header {
-        id,
-        flags: 0x0000,
-        num_questions: 1,
-        ..Default::default()
-    };
-    let question = Question::new(domain_name, record_type, ClassType::IN);
-    header.as_bytes(&mut output);
-    question.as_bytes(&mut output);
-    output
+    QueryBuilder::new().build(domain_name, record_type, id)
 }
 
+/// Build a query with the raw 16-bit header flags word set to `flags`.
+pub fn build_query_with_flags(
+    domain_name: &str,
+    record_type: QueryType,
+    id: u16,
+    flags: u16,
+) -> Vec<u8> {
+    QueryBuilder::new()
+        .flags(flags)
+        .build(domain_name, record_type, id)
+}
+
+/// When deserialized, a record's rdata is encoded afresh from `ty` (see `Record::new`), so the two
+/// always agree.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SerializedRecord")
+)]
 pub struct Record {
     pub name: String,
+    /// the decoded rdata
     pub ty: QueryResponse,
     pub class: ClassType,
     pub ttl: u32,
+    /// the rdata exactly as it appeared on the wire.  Names in it may be compressed, pointing
+    /// elsewhere in the message the record was parsed from, so it's only meaningful alongside
+    /// that message; use `ty` for the decoded form.
     pub data: Vec<u8>,
 }
 
+/// The fields of a serialized `Record` that its rdata is rebuilt from
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SerializedRecord {
+    name: String,
+    ty: QueryResponse,
+    class: ClassType,
+    ttl: u32,
+}
+
+#[cfg(feature = "serde")]
+impl From<SerializedRecord> for Record {
+    fn from(record: SerializedRecord) -> Self {
+        Record::new(&record.name, record.ty, record.class, record.ttl)
+    }
+}
+
 impl Record {
-    fn parse<'a, 'b>(input: &'a [u8], full_input: &'b [u8]) -> IResult<&'a [u8], Self>
+    /// Create a record, encoding its rdata from `ty` so the two always agree.
+    pub fn new(name: &str, ty: QueryResponse, class: ClassType, ttl: u32) -> Self {
+        let mut data = vec![];
+        ty.as_bytes(&mut data);
+        Self {
+            name: name.into(),
+            ty,
+            class,
+            ttl,
+            data,
+        }
+    }
+
+    /// Parse a record, rejecting it if it fails validation when `strict` is set, and otherwise
+    /// only warning about it
+    fn parse<'a, 'b>(input: &'a [u8], full_input: &'b [u8], strict: bool) -> IResult<&'a [u8], Self>
     where
         'b: 'a,
     {
         (
-            |x| -> IResult<&'a [u8], String> { return decode_dns_name(x, full_input) },
-            be_u16.try_map(QueryType::try_from),
-            be_u16.try_map(ClassType::try_from),
+            |x| -> IResult<&'a [u8], String> { decode_dns_name(x, full_input) },
+            be_u16.map(|ty| QueryType::try_from(ty).unwrap_or(QueryType::Unknown(ty))),
+            // the class of an OPT record holds the requestor's UDP payload size instead
+            be_u16,
             be_u32,
-            length_data(be_u16),
+            rdata,
         )
             .try_map(|x| -> color_eyre::Result<Record> {
+                let class = match x.1 {
+                    QueryType::Opt => ClassType::default(),
+                    _ => ClassType::try_from(x.2)?,
+                };
                 let query_response = match x.1 {
-                    QueryType::A => QueryResponse::A(Ipv4Addr::new(x.4[0], x.4[1], x.4[2], x.4[3])),
+                    QueryType::A => match <[u8; 4]>::try_from(x.4) {
+                        Ok(octets) => QueryResponse::A(Ipv4Addr::from(octets)),
+                        Err(_) => QueryResponse::Unknown {
+                            ty: x.1.into(),
+                            data: x.4.to_owned(),
+                        },
+                    },
                     QueryType::Ns => {
                         let name = decode_dns_name(x.4, full_input)
                             .map(|x| x.1)
@@ -220,26 +906,168 @@ impl Record {
                             .context("Failed to parse dns name")?;
                         QueryResponse::Cname(name)
                     }
-                    QueryType::Soa => QueryResponse::Soa,
-                    QueryType::Mb => QueryResponse::Mb,
-                    QueryType::Mg => QueryResponse::Mg,
-                    QueryType::Mr => QueryResponse::Mr,
-                    QueryType::Null => QueryResponse::Null,
-                    QueryType::Wks => QueryResponse::Wks,
-                    QueryType::Ptr => QueryResponse::Ptr,
-                    QueryType::Hinfo => QueryResponse::Hinfo,
-                    QueryType::Minfo => QueryResponse::Minfo,
-                    QueryType::Mx => QueryResponse::Mx,
+                    QueryType::Soa => {
+                        let name = |i| -> IResult<&[u8], String> { decode_dns_name(i, full_input) };
+                        let soa =
+                            (name, name, be_u32, be_u32, be_u32, be_u32, be_u32).map(|x| Soa {
+                                mname: x.0,
+                                rname: x.1,
+                                serial: x.2,
+                                refresh: x.3,
+                                retry: x.4,
+                                expire: x.5,
+                                minimum: x.6,
+                            });
+                        QueryResponse::Soa(
+                            parse_rdata(x.4, soa).context("Failed to parse SOA record")?,
+                        )
+                    }
+                    QueryType::Mb | QueryType::Mg | QueryType::Mr => {
+                        let name = parse_rdata(x.4, |i| decode_dns_name(i, full_input))
+                            .with_context(|| format!("Failed to parse {:?} record", x.1))?;
+                        match x.1 {
+                            QueryType::Mb => QueryResponse::Mb(name),
+                            QueryType::Mg => QueryResponse::Mg(name),
+                            _ => QueryResponse::Mr(name),
+                        }
+                    }
+                    QueryType::Null => QueryResponse::Null(x.4.to_vec()),
+                    QueryType::Wks => {
+                        let (address, protocol, bitmap) =
+                            parse_rdata(x.4, (take(4usize), u8, rest))
+                                .context("Failed to parse WKS record")?;
+                        let ports = bitmap
+                            .iter()
+                            .enumerate()
+                            .flat_map(|(i, byte)| {
+                                (0..8)
+                                    .filter(move |bit| byte & (0x80 >> bit) != 0)
+                                    .map(move |bit| (i * 8 + bit) as u16)
+                            })
+                            .collect();
+                        QueryResponse::Wks {
+                            address: Ipv4Addr::new(address[0], address[1], address[2], address[3]),
+                            protocol,
+                            ports,
+                        }
+                    }
+                    QueryType::Ptr => {
+                        let name = decode_dns_name(x.4, full_input)
+                            .map(|x| x.1)
+                            .map_err(|e| color_eyre::eyre::eyre!("Got error from winnow: {e}"))
+                            .context("Failed to parse dns name")?;
+                        QueryResponse::Ptr(name)
+                    }
+                    QueryType::Hinfo => {
+                        let (cpu, os) = parse_rdata(x.4, (length_data(u8), length_data(u8)))
+                            .context("Failed to parse HINFO record")?;
+                        QueryResponse::Hinfo {
+                            cpu: String::from_utf8_lossy(cpu).to_string(),
+                            os: String::from_utf8_lossy(os).to_string(),
+                        }
+                    }
+                    QueryType::Minfo => {
+                        let name = |i| -> IResult<&[u8], String> { decode_dns_name(i, full_input) };
+                        let (rmailbx, emailbx) = parse_rdata(x.4, (name, name))
+                            .context("Failed to parse MINFO record")?;
+                        QueryResponse::Minfo { rmailbx, emailbx }
+                    }
+                    QueryType::Mx => {
+                        let (preference, exchange) = parse_rdata(
+                            x.4,
+                            (be_u16, |i| -> IResult<&[u8], String> {
+                                decode_dns_name(i, full_input)
+                            }),
+                        )
+                        .context("Failed to parse MX record")?;
+                        QueryResponse::Mx {
+                            preference,
+                            exchange,
+                        }
+                    }
                     QueryType::Txt => QueryResponse::Txt(String::from_utf8_lossy(x.4).to_string()),
-                    QueryType::Aaaa => {
-                        let array: [u8; 16] = x.4.try_into()?;
-                        QueryResponse::Aaaa(Ipv6Addr::from(array))
+                    QueryType::Aaaa => match <[u8; 16]>::try_from(x.4) {
+                        Ok(octets) => QueryResponse::Aaaa(Ipv6Addr::from(octets)),
+                        Err(_) => QueryResponse::Unknown {
+                            ty: x.1.into(),
+                            data: x.4.to_owned(),
+                        },
+                    },
+                    QueryType::Naptr => {
+                        let name = |i| -> IResult<&[u8], String> { decode_dns_name(i, full_input) };
+                        let (order, preference, flags, services, regexp, replacement) =
+                            parse_rdata(
+                                x.4,
+                                (
+                                    be_u16,
+                                    be_u16,
+                                    character_string,
+                                    character_string,
+                                    character_string,
+                                    name,
+                                ),
+                            )
+                            .context("Failed to parse NAPTR record")?;
+                        QueryResponse::Naptr {
+                            order,
+                            preference,
+                            flags,
+                            services,
+                            regexp,
+                            replacement,
+                        }
+                    }
+                    QueryType::Opt => {
+                        let options = parse_rdata(x.4, repeat(0.., EdnsOption::parse))
+                            .context("Failed to parse EDNS options")?;
+                        QueryResponse::Opt {
+                            payload_size: x.2,
+                            options,
+                        }
                     }
+                    QueryType::Rrsig => QueryResponse::Rrsig(
+                        parse_rdata(x.4, |i| Rrsig::parse(i, full_input))
+                            .context("Failed to parse RRSIG record")?,
+                    ),
+                    QueryType::Dnskey => QueryResponse::Dnskey(
+                        parse_rdata(x.4, Dnskey::parse).context("Failed to parse DNSKEY record")?,
+                    ),
+                    QueryType::Cds => QueryResponse::Cds(
+                        parse_rdata(x.4, Ds::parse).context("Failed to parse CDS record")?,
+                    ),
+                    QueryType::Cdnskey => QueryResponse::Cdnskey(
+                        parse_rdata(x.4, Dnskey::parse)
+                            .context("Failed to parse CDNSKEY record")?,
+                    ),
+                    QueryType::Tlsa => {
+                        let (usage, selector, matching_type, cert_data) =
+                            parse_rdata(x.4, (u8, u8, u8, rest))
+                                .context("Failed to parse TLSA record")?;
+                        QueryResponse::Tlsa {
+                            usage,
+                            selector,
+                            matching_type,
+                            cert_data: cert_data.to_vec(),
+                        }
+                    }
+                    QueryType::Openpgpkey => QueryResponse::Openpgpkey(x.4.to_vec()),
+                    QueryType::Unknown(ty) => parse_unknown(ty, x.4, full_input)?,
+                    QueryType::Any | QueryType::Axfr => QueryResponse::Unknown {
+                        ty: x.1.into(),
+                        data: x.4.to_owned(),
+                    },
                 };
+                if let Err(e) = query_response.validate() {
+                    if strict {
+                        return Err(e.into());
+                    }
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(owner = %x.0, "accepting invalid record: {e}");
+                }
                 Ok(Self {
                     name: x.0,
                     ty: query_response,
-                    class: x.2,
+                    class,
                     ttl: x.3,
                     data: x.4.to_owned(),
                 })
@@ -247,32 +1075,442 @@ impl Record {
             .parse_next(input)
     }
 
+    /// The rdata exactly as it appeared on the wire, spanning the record's RDLENGTH bytes
+    pub fn raw_rdata(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Whether this record is owned by `name`, compared with `names_equal`
+    pub fn has_name(&self, name: &str) -> bool {
+        names_equal(&self.name, name)
+    }
+
     pub fn data(&self) -> String {
         match self.ty {
-            QueryResponse::A(addr) => addr.to_string(),
-            QueryResponse::Ns(ref nameserver) => nameserver.clone(),
-            QueryResponse::Cname(ref name) => name.to_string(),
-            QueryResponse::Aaaa(addr) => addr.to_string(),
-            QueryResponse::Txt(ref data) => data.clone(),
-            _ => format!("\"{:?}\"", &self.data),
+            QueryResponse::Md | QueryResponse::Mf => format!("\"{:?}\"", &self.data),
+            ref ty => ty.to_string(),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Response {
-    header: Header,
-    questions: Vec<Question>,
-    answers: Vec<Record>,
-    authorities: Vec<Record>,
-    additionals: Vec<Record>,
-}
+impl Record {
+    /// This record as a line of a zone file, with every name fully qualified
+    fn to_zone_line(&self) -> String {
+        self.fields(" ")
+    }
 
-impl Response {
-    pub fn parse(input: &[u8]) -> color_eyre::Result<Self> {
-        let (remaining, header) = Header::parse(input).map_err(|e| {
-            color_eyre::eyre::eyre!("Failed to parse header").wrap_err(format!("{:?}", e))
-        })?;
+    /// This record as a line of dig's output: a zone file line, with tabs between the fields
+    fn to_dig_line(&self) -> String {
+        self.fields("\t")
+    }
+
+    /// The fields of a zone file line for this record, joined with `sep`
+    fn fields(&self, sep: &str) -> String {
+        [
+            absolute(&self.name),
+            self.ttl.to_string(),
+            format!("{:?}", self.class),
+            self.zone_type(),
+            self.zone_data(),
+        ]
+        .join(sep)
+    }
+
+    /// The record's type as written in a zone file, using the `TYPEnnn` form for unknown types
+    fn zone_type(&self) -> String {
+        match QueryType::from(&self.ty) {
+            QueryType::Unknown(code) => format!("TYPE{code}"),
+            _ => self.ty.name().to_string(),
+        }
+    }
+
+    /// The record's data as written in a zone file, with every name fully qualified
+    fn zone_data(&self) -> String {
+        match &self.ty {
+            QueryResponse::Ns(name)
+            | QueryResponse::Cname(name)
+            | QueryResponse::Ptr(name)
+            | QueryResponse::Mb(name)
+            | QueryResponse::Mg(name)
+            | QueryResponse::Mr(name) => absolute(name),
+            QueryResponse::Mx {
+                preference,
+                exchange,
+            } => format!("{preference} {}", absolute(exchange)),
+            QueryResponse::Minfo { rmailbx, emailbx } => {
+                format!("{} {}", absolute(rmailbx), absolute(emailbx))
+            }
+            QueryResponse::Soa(soa) => Soa {
+                mname: absolute(&soa.mname),
+                rname: absolute(&soa.rname),
+                ..soa.clone()
+            }
+            .to_string(),
+            QueryResponse::Naptr { replacement, .. } if !replacement.is_empty() => {
+                let mut ty = self.ty.clone();
+                if let QueryResponse::Naptr { replacement, .. } = &mut ty {
+                    *replacement = absolute(replacement);
+                }
+                ty.to_string()
+            }
+            QueryResponse::Rrsig(rrsig) => Rrsig {
+                signer_name: absolute(&rrsig.signer_name),
+                ..rrsig.clone()
+            }
+            .to_string(),
+            // NULL has no presentation format, so use the RFC 3597 generic one
+            QueryResponse::Null(data) => format!("\\# {} {}", data.len(), to_hex(data)),
+            QueryResponse::Txt(_) => {
+                let mut strings = vec![];
+                let mut rdata = self.data.as_slice();
+                while let Ok((rest, string)) = character_string(rdata) {
+                    strings.push(quote(&string));
+                    rdata = rest;
+                }
+                strings.join(" ")
+            }
+            _ => self.data(),
+        }
+    }
+}
+
+/// `name` with the trailing dot that marks it as fully qualified in a zone file
+fn absolute(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+impl Display for Record {
+    /// The record in zone-file presentation format: `name TTL class type data`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = if self.name.is_empty() {
+            "."
+        } else {
+            &self.name
+        };
+        write!(
+            f,
+            "{name} {} {:?} {} {}",
+            self.ttl,
+            self.class,
+            self.ty.name(),
+            self.data()
+        )
+    }
+}
+
+impl Display for QueryResponse {
+    /// The presentation format of the record's data.  Types this crate doesn't parse the data of
+    /// are written as nothing.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryResponse::A(addr) => write!(f, "{addr}"),
+            QueryResponse::Ns(name)
+            | QueryResponse::Cname(name)
+            | QueryResponse::Ptr(name)
+            | QueryResponse::Mb(name)
+            | QueryResponse::Mg(name)
+            | QueryResponse::Mr(name) => {
+                write!(f, "{name}")
+            }
+            QueryResponse::Aaaa(addr) => write!(f, "{addr}"),
+            QueryResponse::Txt(data) => write!(f, "{data}"),
+            QueryResponse::Hinfo { cpu, os } => write!(f, "{cpu} {os}"),
+            QueryResponse::Naptr {
+                order,
+                preference,
+                flags,
+                services,
+                regexp,
+                replacement,
+            } => write!(
+                f,
+                "{order} {preference} {} {} {} {}",
+                quote(flags),
+                quote(services),
+                quote(regexp),
+                if replacement.is_empty() {
+                    "."
+                } else {
+                    replacement
+                }
+            ),
+            QueryResponse::Soa(soa) => write!(f, "{soa}"),
+            QueryResponse::Wks {
+                address,
+                protocol,
+                ports,
+            } => {
+                write!(f, "{address} {protocol}")?;
+                ports.iter().try_for_each(|port| write!(f, " {port}"))
+            }
+            QueryResponse::Mx {
+                preference,
+                exchange,
+            } => write!(f, "{preference} {exchange}"),
+            QueryResponse::Minfo { rmailbx, emailbx } => write!(f, "{rmailbx} {emailbx}"),
+            QueryResponse::Opt {
+                payload_size,
+                options,
+            } => {
+                write!(f, "udp={payload_size}")?;
+                options.iter().try_for_each(|option| write!(f, " {option}"))
+            }
+            QueryResponse::Rrsig(rrsig) => write!(f, "{rrsig}"),
+            QueryResponse::Dnskey(dnskey) | QueryResponse::Cdnskey(dnskey) => {
+                write!(f, "{dnskey}")
+            }
+            QueryResponse::Cds(ds) => write!(f, "{ds}"),
+            QueryResponse::Tlsa {
+                usage,
+                selector,
+                matching_type,
+                cert_data,
+            } => write!(
+                f,
+                "{usage} {selector} {matching_type} {}",
+                to_hex(cert_data).to_uppercase()
+            ),
+            QueryResponse::Openpgpkey(key) => write!(f, "{}", to_hex(key).to_uppercase()),
+            QueryResponse::Null(data) => write!(f, "{}", to_hex(data)),
+            QueryResponse::Custom { data, .. } => write!(f, "{data}"),
+            QueryResponse::Unknown { data, .. } => {
+                // RFC 3597 generic rdata encoding
+                write!(f, "\\# {} {}", data.len(), to_hex(data))
+            }
+            QueryResponse::Md | QueryResponse::Mf => Ok(()),
+        }
+    }
+}
+
+impl AsBytes for Record {
+    fn as_bytes<T>(&self, dest: &mut T)
+    where
+        T: std::io::Write,
+    {
+        let _ = dest.write_all(&encode_dns_name(&self.name));
+        let _ = dest.write_all(&u16::from(QueryType::from(&self.ty)).to_be_bytes());
+        let class = match self.ty {
+            QueryResponse::Opt { payload_size, .. } => payload_size,
+            _ => self.class as u16,
+        };
+        let _ = dest.write_all(&class.to_be_bytes());
+        let _ = dest.write_all(&self.ttl.to_be_bytes());
+        let _ = dest.write_all(&(self.data.len() as u16).to_be_bytes());
+        let _ = dest.write_all(&self.data);
+    }
+}
+
+/// Encodes the rdata of a record, with any names in it uncompressed.  Types without data, and
+/// custom types (whose presentation format can't be turned back into rdata), encode as nothing.
+impl AsBytes for QueryResponse {
+    fn as_bytes<T>(&self, dest: &mut T)
+    where
+        T: std::io::Write,
+    {
+        let character_string = |dest: &mut T, string: &str| {
+            let _ = dest.write_all(&[string.len() as u8]);
+            let _ = dest.write_all(string.as_bytes());
+        };
+        match self {
+            QueryResponse::A(address) => {
+                let _ = dest.write_all(&address.octets());
+            }
+            QueryResponse::Aaaa(address) => {
+                let _ = dest.write_all(&address.octets());
+            }
+            QueryResponse::Ns(name)
+            | QueryResponse::Cname(name)
+            | QueryResponse::Ptr(name)
+            | QueryResponse::Mb(name)
+            | QueryResponse::Mg(name)
+            | QueryResponse::Mr(name) => {
+                let _ = dest.write_all(&encode_dns_name(name));
+            }
+            QueryResponse::Soa(soa) => {
+                let _ = dest.write_all(&encode_dns_name(&soa.mname));
+                let _ = dest.write_all(&encode_dns_name(&soa.rname));
+                for x in [soa.serial, soa.refresh, soa.retry, soa.expire, soa.minimum] {
+                    let _ = dest.write_all(&x.to_be_bytes());
+                }
+            }
+            QueryResponse::Wks {
+                address,
+                protocol,
+                ports,
+            } => {
+                let _ = dest.write_all(&address.octets());
+                let _ = dest.write_all(&[*protocol]);
+                let len = ports.iter().max().map_or(0, |max| *max as usize / 8 + 1);
+                let mut bitmap = vec![0u8; len];
+                for port in ports {
+                    bitmap[*port as usize / 8] |= 0x80 >> (port % 8);
+                }
+                let _ = dest.write_all(&bitmap);
+            }
+            QueryResponse::Minfo { rmailbx, emailbx } => {
+                let _ = dest.write_all(&encode_dns_name(rmailbx));
+                let _ = dest.write_all(&encode_dns_name(emailbx));
+            }
+            QueryResponse::Hinfo { cpu, os } => {
+                character_string(dest, cpu);
+                character_string(dest, os);
+            }
+            QueryResponse::Mx {
+                preference,
+                exchange,
+            } => {
+                let _ = dest.write_all(&preference.to_be_bytes());
+                let _ = dest.write_all(&encode_dns_name(exchange));
+            }
+            QueryResponse::Txt(text) => {
+                let _ = dest.write_all(text.as_bytes());
+            }
+            QueryResponse::Naptr {
+                order,
+                preference,
+                flags,
+                services,
+                regexp,
+                replacement,
+            } => {
+                let _ = dest.write_all(&order.to_be_bytes());
+                let _ = dest.write_all(&preference.to_be_bytes());
+                character_string(dest, flags);
+                character_string(dest, services);
+                character_string(dest, regexp);
+                let _ = dest.write_all(&encode_dns_name(replacement));
+            }
+            QueryResponse::Opt { options, .. } => {
+                for option in options {
+                    option.as_bytes(dest);
+                }
+            }
+            QueryResponse::Rrsig(rrsig) => {
+                let _ = dest.write_all(&u16::from(rrsig.type_covered).to_be_bytes());
+                let _ = dest.write_all(&[rrsig.algorithm, rrsig.labels]);
+                for x in [rrsig.original_ttl, rrsig.expiration, rrsig.inception] {
+                    let _ = dest.write_all(&x.to_be_bytes());
+                }
+                let _ = dest.write_all(&rrsig.key_tag.to_be_bytes());
+                let _ = dest.write_all(&encode_dns_name(&rrsig.signer_name));
+                let _ = dest.write_all(&rrsig.signature);
+            }
+            QueryResponse::Dnskey(key) | QueryResponse::Cdnskey(key) => {
+                let _ = dest.write_all(&key.flags.to_be_bytes());
+                let _ = dest.write_all(&[key.protocol, key.algorithm]);
+                let _ = dest.write_all(&key.public_key);
+            }
+            QueryResponse::Cds(ds) => {
+                let _ = dest.write_all(&ds.key_tag.to_be_bytes());
+                let _ = dest.write_all(&[ds.algorithm, ds.digest_type]);
+                let _ = dest.write_all(&ds.digest);
+            }
+            QueryResponse::Tlsa {
+                usage,
+                selector,
+                matching_type,
+                cert_data,
+            } => {
+                let _ = dest.write_all(&[*usage, *selector, *matching_type]);
+                let _ = dest.write_all(cert_data);
+            }
+            QueryResponse::Openpgpkey(data) | QueryResponse::Null(data) => {
+                let _ = dest.write_all(data);
+            }
+            QueryResponse::Unknown { data, .. } => {
+                let _ = dest.write_all(data);
+            }
+            QueryResponse::Custom { .. } => {}
+            QueryResponse::Md | QueryResponse::Mf => {}
+        }
+    }
+}
+
+/// The section of a response a record appears in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Section {
+    Answer,
+    Authority,
+    Additional,
+}
+
+/// What a response tells an iterative resolver, from `Response::classify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseKind {
+    /// the answer section holds records
+    Answer,
+    /// no answers, but a delegation to the nameservers `ns`
+    Referral { ns: Vec<String> },
+    /// no answers and nowhere else to ask
+    NoData,
+    /// the server reported an error
+    Error(ResponseCode),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Response {
+    header: Header,
+    questions: Vec<Question>,
+    answers: Vec<Record>,
+    authorities: Vec<Record>,
+    additionals: Vec<Record>,
+}
+
+impl Response {
+    /// Parse a response, failing if any of its records are invalid
+    pub fn parse(input: &[u8]) -> color_eyre::Result<Self> {
+        Self::parse_with_validation(input, true)
+    }
+
+    /// Read one message framed with a two-byte length prefix, as sent over TCP, from `reader`
+    /// and parse it
+    pub fn parse_from<R: Read>(reader: &mut R) -> color_eyre::Result<Self> {
+        let message = read_tcp_message(reader).context("Failed to read message")?;
+        Self::parse(&message)
+    }
+
+    /// Parse a response saved to `path` in wire format, e.g. by the CLI's `--capture` option
+    pub fn from_bytes_file(path: impl AsRef<Path>) -> color_eyre::Result<Self> {
+        let path = path.as_ref();
+        let input = std::fs::read(path)
+            .with_context(|| format!("Unable to read response from {}", path.display()))?;
+        Self::parse(&input)
+    }
+
+    /// Parse a response, accepting invalid records instead of failing.  With the `tracing` feature,
+    /// a warning is logged for each.
+    pub fn parse_lenient(input: &[u8]) -> color_eyre::Result<Self> {
+        Self::parse_with_validation(input, false)
+    }
+
+    fn parse_with_validation(input: &[u8], strict: bool) -> color_eyre::Result<Self> {
+        let (remaining, header) = Header::parse(input).map_err(|e| {
+            color_eyre::eyre::eyre!("Failed to parse header").wrap_err(format!("{:?}", e))
+        })?;
+
+        // reject counts the input can't possibly hold before allocating room for them
+        let num_records = header.num_answers as usize
+            + header.num_authorities as usize
+            + header.num_additionals as usize;
+        let min_size =
+            header.num_questions as usize * MIN_QUESTION_SIZE + num_records * MIN_RECORD_SIZE;
+        if min_size > remaining.len() {
+            color_eyre::eyre::bail!(
+                "Header claims {} questions and {num_records} records, but only {} bytes follow it",
+                header.num_questions,
+                remaining.len()
+            );
+        }
 
         let (questions, answers, authorities, additionals) = (
             repeat(
@@ -280,20 +1518,26 @@ impl Response {
                 |x| -> IResult<&[u8], Question> { Question::parse(x, input) },
             ),
             repeat(header.num_answers as usize, |x| -> IResult<&[u8], Record> {
-                Record::parse(x, input)
+                Record::parse(x, input, strict)
             }),
             repeat(
                 header.num_authorities as usize,
-                |x| -> IResult<&[u8], Record> { Record::parse(x, input) },
+                |x| -> IResult<&[u8], Record> { Record::parse(x, input, strict) },
             ),
             repeat(
                 header.num_additionals as usize,
-                |x| -> IResult<&[u8], Record> { Record::parse(x, input) },
+                |x| -> IResult<&[u8], Record> { Record::parse(x, input, strict) },
             ),
         )
             .parse(remaining)
-            .map_err(|e| {
-                color_eyre::eyre::eyre!("Failed to parse body").wrap_err(format!("{:?}", e))
+            .map_err(|e| match e.kind {
+                // raised by `rdata` at the rdlength field
+                ErrorKind::Complete if e.input.len() >= 2 => ParseError::RdataTooLong {
+                    claimed: u16::from_be_bytes([e.input[0], e.input[1]]),
+                    available: e.input.len() - 2,
+                }
+                .into(),
+                _ => color_eyre::eyre::eyre!("Failed to parse body").wrap_err(format!("{:?}", e)),
             })?;
 
         Ok(Response {
@@ -305,10 +1549,46 @@ impl Response {
         })
     }
 
+    /// Whether the response came from a server authoritative for the queried name, rather than
+    /// from a recursive resolver's cache
+    pub fn is_authoritative(&self) -> bool {
+        self.header.authoritative()
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Whether the header breaks the protocol in ways that don't stop the response being used:
+    /// reserved bits that are set, or a clear QR bit.  Useful for conformance testing servers.
+    pub fn has_protocol_anomalies(&self) -> bool {
+        self.header.reserved_bits() != 0 || !self.header.response()
+    }
+
+    pub fn questions(&self) -> impl Iterator<Item = &Question> {
+        self.questions.iter()
+    }
+
     pub fn answers(&self) -> impl Iterator<Item = &Record> {
         self.answers.iter()
     }
 
+    /// The address in the first A or AAAA answer, whatever its name
+    pub fn first_address(&self) -> Option<IpAddr> {
+        self.answers.iter().find_map(|record| match record.ty {
+            QueryResponse::A(address) => Some(address.into()),
+            QueryResponse::Aaaa(address) => Some(address.into()),
+            _ => None,
+        })
+    }
+
+    /// The answers of type `ty`, whatever their name.  `QueryType::Any` matches every type.
+    pub fn answers_of_type(&self, ty: QueryType) -> impl Iterator<Item = &Record> {
+        self.answers
+            .iter()
+            .filter(move |record| ty == QueryType::Any || QueryType::from(&record.ty) == ty)
+    }
+
     pub fn authorities(&self) -> impl Iterator<Item = &Record> {
         self.authorities.iter()
     }
@@ -316,6 +1596,338 @@ impl Response {
     pub fn additionals(&self) -> impl Iterator<Item = &Record> {
         self.additionals.iter()
     }
+
+    /// The outcome of the query, combining the header's RCODE with the upper 8 bits of the
+    /// extended RCODE in the OPT record, if there is one ([RFC 6891 section
+    /// 6.1.3](https://datatracker.ietf.org/doc/html/rfc6891#section-6.1.3))
+    pub fn response_code(&self) -> ResponseCode {
+        let extended = self.opt().map_or(0, |opt| (opt.ttl >> 24) as u16);
+        ResponseCode::from(extended << 4 | u16::from(self.header.rcode()))
+    }
+
+    /// Whether this response answers the query, refers it elsewhere, or neither
+    pub fn classify(&self) -> ResponseKind {
+        match self.response_code() {
+            ResponseCode::NoError => {}
+            code => return ResponseKind::Error(code),
+        }
+        if !self.answers.is_empty() {
+            return ResponseKind::Answer;
+        }
+        let ns: Vec<_> = self
+            .authorities()
+            .filter_map(|record| match &record.ty {
+                QueryResponse::Ns(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        if ns.is_empty() {
+            ResponseKind::NoData
+        } else {
+            ResponseKind::Referral { ns }
+        }
+    }
+
+    /// The smallest TTL among the answers, which is how long the response as a whole may be
+    /// cached for
+    pub fn min_ttl(&self) -> Option<u32> {
+        self.min_ttl_in(&[Section::Answer])
+    }
+
+    /// The smallest TTL among the records in `sections`.  The OPT pseudo-record, whose TTL field
+    /// holds flags, is ignored.
+    pub fn min_ttl_in(&self, sections: &[Section]) -> Option<u32> {
+        self.all_records()
+            .filter(|(section, record)| {
+                sections.contains(section) && !matches!(record.ty, QueryResponse::Opt { .. })
+            })
+            .map(|(_, record)| record.ttl)
+            .min()
+    }
+
+    /// How long a negative response (NXDOMAIN or NODATA) may be cached for: the smaller of the
+    /// authority SOA record's TTL and its MINIMUM field ([RFC 2308 section
+    /// 5](https://datatracker.ietf.org/doc/html/rfc2308#section-5)).  `None` without a SOA.
+    pub fn negative_ttl(&self) -> Option<u32> {
+        self.authorities.iter().find_map(|record| match &record.ty {
+            QueryResponse::Soa(soa) => Some(record.ttl.min(soa.minimum)),
+            _ => None,
+        })
+    }
+
+    /// A hash of the answer section that ignores TTLs and record order, so two responses with
+    /// the same answers have the same fingerprint.  The hash is stable across runs and builds.
+    pub fn fingerprint(&self) -> u64 {
+        let mut answers: Vec<_> = self
+            .answers
+            .iter()
+            .map(|record| {
+                (
+                    record.name.to_ascii_lowercase(),
+                    u16::from(QueryType::from(&record.ty)),
+                    record.class as u16,
+                    record.data(),
+                )
+            })
+            .collect();
+        answers.sort();
+
+        // 64-bit FNV-1a
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        for (name, ty, class, data) in &answers {
+            write(&(name.len() as u64).to_be_bytes());
+            write(name.as_bytes());
+            write(&ty.to_be_bytes());
+            write(&class.to_be_bytes());
+            write(&(data.len() as u64).to_be_bytes());
+            write(data.as_bytes());
+        }
+        hash
+    }
+
+    /// Remove authority and additional records whose owner is outside of `zone`, returning the
+    /// removed records.  The OPT pseudo-record is always kept.
+    pub fn discard_out_of_bailiwick(&mut self, zone: &str) -> Vec<Record> {
+        let mut discarded = vec![];
+        for section in [&mut self.authorities, &mut self.additionals] {
+            let (keep, discard) = std::mem::take(section).into_iter().partition(|record| {
+                matches!(record.ty, QueryResponse::Opt { .. }) || in_bailiwick(&record.name, zone)
+            });
+            *section = keep;
+            discarded.extend::<Vec<_>>(discard);
+        }
+        discarded
+    }
+
+    /// The OPT pseudo-record, if the server included one
+    pub fn opt(&self) -> Option<&Record> {
+        self.additionals
+            .iter()
+            .find(|record| matches!(record.ty, QueryResponse::Opt { .. }))
+    }
+
+    /// All EDNS options carried by the OPT pseudo-record, in the order they were sent,
+    /// including ones this crate doesn't interpret.
+    pub fn edns_options(&self) -> impl Iterator<Item = &EdnsOption> {
+        self.opt().into_iter().flat_map(|record| match record.ty {
+            QueryResponse::Opt { ref options, .. } => options.as_slice(),
+            _ => &[],
+        })
+    }
+
+    /// Whether a CNAME in the answer section shares its owner name with any record other than
+    /// another CNAME or the DNSSEC records (RRSIG and NSEC) allowed alongside it
+    pub fn has_cname_coexistence_error(&self) -> bool {
+        const NSEC: u16 = 47;
+        let cnames: HashSet<_> = self
+            .answers
+            .iter()
+            .filter(|record| matches!(record.ty, QueryResponse::Cname(_)))
+            .map(|record| record.name.to_ascii_lowercase())
+            .collect();
+        self.answers.iter().any(|record| {
+            !matches!(record.ty, QueryResponse::Cname(_) | QueryResponse::Rrsig(_))
+                && u16::from(QueryType::from(&record.ty)) != NSEC
+                && cnames.contains(&record.name.to_ascii_lowercase())
+        })
+    }
+
+    /// The addresses of every A and AAAA record in the answer section
+    pub fn extract_addresses(&self) -> Vec<IpAddr> {
+        self.answers
+            .iter()
+            .filter_map(|record| match record.ty {
+                QueryResponse::A(address) => Some(address.into()),
+                QueryResponse::Aaaa(address) => Some(address.into()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every record in the response, tagged with the section it came from
+    pub fn all_records(&self) -> impl Iterator<Item = (Section, &Record)> {
+        let answers = self.answers.iter().map(|record| (Section::Answer, record));
+        let authorities = self
+            .authorities
+            .iter()
+            .map(|record| (Section::Authority, record));
+        let additionals = self
+            .additionals
+            .iter()
+            .map(|record| (Section::Additional, record));
+        answers.chain(authorities).chain(additionals)
+    }
+
+    /// The answer and authority records in zone-file (master file) format, one per line, as
+    /// described in [RFC 1035 section
+    /// 5.1](https://datatracker.ietf.org/doc/html/rfc1035#section-5.1)
+    pub fn to_zone_file(&self) -> String {
+        self.answers
+            .iter()
+            .chain(&self.authorities)
+            .filter(|record| !matches!(record.ty, QueryResponse::Opt { .. }))
+            .map(|record| record.to_zone_line() + "\n")
+            .collect()
+    }
+
+    /// The response laid out like dig's output: the header, flags and section counts, the EDNS
+    /// pseudosection if there's an OPT record, then each non-empty section.  dig's query-time
+    /// footer depends on how the response was received, so is left to the caller.
+    pub fn to_dig_format(&self) -> String {
+        let header = &self.header;
+        let mut output = format!(
+            ";; ->>HEADER<<- opcode: {}, status: {}, id: {}\n",
+            header.opcode(),
+            self.response_code(),
+            header.id
+        );
+        let flags = [
+            ("qr", FLAG_QR),
+            ("aa", FLAG_AA),
+            ("tc", FLAG_TC),
+            ("rd", FLAG_RD),
+            ("ra", FLAG_RA),
+            ("ad", FLAG_AD),
+            ("cd", FLAG_CD),
+        ];
+        let flags: Vec<_> = flags
+            .into_iter()
+            .filter(|(_, bit)| header.flags & bit != 0)
+            .map(|(name, _)| name)
+            .collect();
+        output += &format!(
+            ";; flags: {}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}\n",
+            flags.join(" "),
+            header.num_questions,
+            header.num_answers,
+            header.num_authorities,
+            header.num_additionals
+        );
+        if let Some(opt) = self.opt() {
+            let payload_size = match opt.ty {
+                QueryResponse::Opt { payload_size, .. } => payload_size,
+                _ => 0,
+            };
+            let do_flag = if opt.ttl & EDNS_FLAG_DO != 0 {
+                " do"
+            } else {
+                ""
+            };
+            output += &format!(
+                "\n;; OPT PSEUDOSECTION:\n; EDNS: version: {}, flags:{do_flag}; udp: {payload_size}\n",
+                (opt.ttl >> 16) & 0xff
+            );
+        }
+        if !self.questions.is_empty() {
+            output += "\n;; QUESTION SECTION:\n";
+            for question in &self.questions {
+                output += &format!(
+                    ";{}\t\t{:?}\t{}\n",
+                    absolute(&question.name),
+                    question.class,
+                    question.ty.to_possible_value().map_or_else(
+                        || format!("TYPE{}", u16::from(question.ty)),
+                        |value| value.get_name().to_string()
+                    )
+                );
+            }
+        }
+        for (title, records) in [
+            ("ANSWER", &self.answers),
+            ("AUTHORITY", &self.authorities),
+            ("ADDITIONAL", &self.additionals),
+        ] {
+            let lines: Vec<_> = records
+                .iter()
+                .filter(|record| !matches!(record.ty, QueryResponse::Opt { .. }))
+                .map(Record::to_dig_line)
+                .collect();
+            if !lines.is_empty() {
+                output += &format!("\n;; {title} SECTION:\n");
+                for line in lines {
+                    output += &line;
+                    output += "\n";
+                }
+            }
+        }
+        output
+    }
+
+    /// The answers of type `ty` (or of every type, for `QueryType::Any`) for `name`, including
+    /// those for the targets of any CNAMEs in the answer section leading from `name`.  Names are
+    /// compared with `names_equal`.
+    pub fn answers_for<'a>(
+        &'a self,
+        name: &'a str,
+        ty: QueryType,
+    ) -> impl Iterator<Item = &'a Record> + 'a {
+        let mut names = vec![name];
+        // each step of the chain uses up a different answer, which bounds the walk if it loops
+        for _ in 0..self.answers.len() {
+            let current = *names.last().unwrap();
+            let Some(target) = self.answers.iter().find_map(|record| match &record.ty {
+                QueryResponse::Cname(target)
+                    if record.has_name(current)
+                        && !names.iter().any(|name| names_equal(name, target)) =>
+                {
+                    Some(target.as_str())
+                }
+                _ => None,
+            }) else {
+                break;
+            };
+            names.push(target);
+        }
+        self.answers_of_type(ty)
+            .filter(move |record| names.iter().any(|name| record.has_name(name)))
+    }
+
+    /// The server cookie the server sent, to send back with later queries to it
+    pub fn server_cookie(&self) -> Option<&[u8]> {
+        self.edns_options().find_map(|option| match option {
+            EdnsOption::Cookie { server, .. } if !server.is_empty() => Some(server.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Check that any DNS cookie in the response is well-formed and echoes the client cookie
+    /// `client` we sent.  Servers that don't support cookies leave them out, which is allowed.
+    pub fn verify_cookie(&self, client: [u8; 8]) -> Result<(), DnsError> {
+        for option in self.edns_options() {
+            match option {
+                EdnsOption::Cookie { client: echoed, .. } if *echoed == client => {}
+                // a cookie of the wrong length is kept as an unknown option
+                EdnsOption::Cookie { .. } | EdnsOption::Unknown { code: 10, .. } => {
+                    return Err(DnsError::BadCookie)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the question echoed back by the server matches the one we asked.
+    pub fn verify_question(&self, expected: &Question) -> Result<(), DnsError> {
+        match self.questions.first() {
+            Some(received)
+                if names_equal(&received.name, &expected.name)
+                    && received.ty == expected.ty
+                    && received.class == expected.class =>
+            {
+                Ok(())
+            }
+            received => Err(DnsError::QuestionMismatch {
+                expected: expected.clone(),
+                received: received.cloned(),
+            }),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -350,6 +1962,59 @@ mod test {
     fn test_encode_dns_name() {
         let output = encode_dns_name("google.com");
         assert_eq!(output, b"\x06google\x03com\x00");
+        assert_eq!(encode_dns_name("google.com."), output);
+        assert_eq!(encode_dns_name(""), b"\x00");
+    }
+
+    #[test]
+    fn test_pointer_out_of_bounds() {
+        let input = b"\x02pi\xc0\x06";
+        let error = decode_dns_name(input, input).unwrap_err();
+        assert_eq!(
+            error,
+            ErrMode::Cut(Error::new(&b"\xc0\x06"[..], ErrorKind::Eof))
+        );
+    }
+
+    #[test]
+    fn test_reserved_label_types() {
+        for head in [0x40u8, 0x7f, 0x80, 0xbf] {
+            let mut input = vec![0x02, b'p', b'i', head];
+            input.extend([b'x'; 0xbf]);
+            let error = decode_dns_name(&input, &input).unwrap_err();
+            assert_eq!(
+                error,
+                ErrMode::Cut(Error::new(&[head][..], ErrorKind::Verify))
+            );
+        }
+    }
+
+    #[test]
+    fn test_forward_pointer() {
+        // a pointer to itself
+        let input = b"\x02pi\xc0\x03\x00";
+        let error = decode_dns_name(input, input).unwrap_err();
+        assert_eq!(
+            error,
+            ErrMode::Cut(Error::new(&b"\xc0\x03"[..], ErrorKind::Verify))
+        );
+
+        // a pointer past itself
+        let input = b"\xc0\x02\x02pi\x00";
+        assert!(decode_dns_name(input, input).is_err());
+
+        // a pointer to an earlier name is fine
+        let input = b"\x02pi\x00\x04hole\xc0\x00";
+        let (_, name) = decode_dns_name(&input[4..], input).unwrap();
+        assert_eq!(name, "hole.pi");
+    }
+
+    #[test]
+    fn test_escaped_labels() {
+        let input = b"\x03a\x07b\x04c.d\\\x00";
+        let (_, name) = decode_dns_name(input, input).unwrap();
+        assert_eq!(name, "a\\007b.c\\.d\\\\");
+        assert_eq!(encode_dns_name(&name), input);
     }
 
     #[test]
@@ -359,6 +2024,70 @@ mod test {
         assert_eq!(query, b"\x00\x01\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x06google\x03com\x00\x00\x01\x00\x01")
     }
 
+    #[test]
+    fn test_build_query_with_flags() {
+        let query = build_query_with_flags("google.com", QueryType::A, 1, 0x0120);
+        assert_eq!(&query[2..4], b"\x01\x20");
+
+        let query = QueryBuilder::new()
+            .flags(0x0020)
+            .recursion_desired(true)
+            .build("google.com", QueryType::A, 1);
+        assert_eq!(&query[2..4], b"\x01\x20");
+    }
+
+    #[test]
+    fn test_build_iterative_query() {
+        let builder = QueryBuilder::new().recursion_desired(false);
+        let query = builder.build("example.com", QueryType::Mx, 0xbeef);
+        assert_eq!(query, b"\xbe\xef\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x07example\x03com\x00\x00\x0f\x00\x01");
+        assert_eq!(builder.build("example.com", QueryType::Mx, 0xbeef), query);
+
+        let query = builder
+            .flags(0x0010)
+            .build("example.com", QueryType::Mx, 0xbeef);
+        assert_eq!(query, b"\xbe\xef\x00\x10\x00\x01\x00\x00\x00\x00\x00\x00\x07example\x03com\x00\x00\x0f\x00\x01");
+    }
+
+    #[test]
+    fn test_build_query_with_opcode() {
+        let query = QueryBuilder::new()
+            .recursion_desired(true)
+            .opcode(Opcode::Notify)
+            .build("example.com", QueryType::Soa, 1);
+        assert_eq!(&query[2..4], b"\x21\x00");
+        let (_, header) = Header::parse(&query).unwrap();
+        assert_eq!(header.opcode(), Opcode::Notify);
+
+        let query = QueryBuilder::new()
+            .opcode(Opcode::Status)
+            .opcode(Opcode::Other(9))
+            .build("example.com", QueryType::Soa, 1);
+        let (_, header) = Header::parse(&query).unwrap();
+        assert_eq!(header.opcode(), Opcode::Other(9));
+
+        let (_, header) =
+            Header::parse(&QueryBuilder::new().build("example.com", QueryType::A, 1)).unwrap();
+        assert_eq!(header.opcode(), Opcode::Query);
+    }
+
+    #[test]
+    fn test_build_query_with_dnssec_bits() {
+        let query = QueryBuilder::new()
+            .checking_disabled(true)
+            .dnssec_ok(true)
+            .build("google.com", QueryType::A, 1);
+        assert_eq!(query, b"\x00\x01\x00\x10\x00\x01\x00\x00\x00\x00\x00\x01\x06google\x03com\x00\x00\x01\x00\x01\x00\x00\x29\x04\xd0\x00\x00\x80\x00\x00\x00");
+
+        let opt = Response::parse(&query).unwrap().opt().cloned().unwrap();
+        assert_eq!(opt.ttl & EDNS_FLAG_DO, EDNS_FLAG_DO);
+
+        let query = QueryBuilder::new()
+            .edns(4096)
+            .build("google.com", QueryType::A, 1);
+        assert!(query.ends_with(b"\x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x00"));
+    }
+
     #[test]
     fn test_parse_header() {
         let header = Header {
@@ -428,4 +2157,1275 @@ mod test {
             }]
         )
     }
+
+    #[test]
+    fn test_is_authoritative() {
+        let response = b"\x00\x01\x85\x80\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert!(Response::parse(response).unwrap().is_authoritative());
+
+        let response = b"\x00\x01\x81\x80\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert!(!Response::parse(response).unwrap().is_authoritative());
+    }
+
+    #[test]
+    fn test_questions() {
+        let response = b"\x00\x01\x85\x80\x00\x01\x00\x00\x00\x00\x00\x00\x02\x70\x69\x04\x68\x6f\x6c\x65\x00\x00\x1c\x00\x01";
+        let response = Response::parse(response).unwrap();
+        let questions: Vec<_> = response.questions().collect();
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].name(), "pi.hole");
+        assert_eq!(questions[0].ty(), QueryType::Aaaa);
+        assert_eq!(questions[0].class(), ClassType::IN);
+    }
+
+    #[test]
+    fn test_all_records() {
+        let response = b"\x00\x01\x85\x80\x00\x01\x00\x01\x00\x00\x00\x01\x02\x70\x69\x04\x68\x6f\x6c\x65\x00\x00\x01\x00\x01\xc0\x0c\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\xc0\xa8\x02\x66\xc0\x0c\xff\x00\x00\x01\x00\x00\x00\x0e\x00\x03\x01\x02\x03";
+        let response = Response::parse(response).unwrap();
+
+        let sections: Vec<_> = response
+            .all_records()
+            .map(|(section, record)| (section, record.ty.name()))
+            .collect();
+        assert_eq!(
+            sections,
+            [(Section::Answer, "A"), (Section::Additional, "UNKNOWN")]
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_record_type() {
+        let response = b"\x00\x01\x85\x80\x00\x01\x00\x01\x00\x00\x00\x01\x02\x70\x69\x04\x68\x6f\x6c\x65\x00\x00\x01\x00\x01\xc0\x0c\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\xc0\xa8\x02\x66\xc0\x0c\xff\x00\x00\x01\x00\x00\x00\x0e\x00\x03\x01\x02\x03";
+        let response = Response::parse(response);
+        assert!(response.is_ok());
+
+        let response = response.unwrap();
+        assert_eq!(response.answers.len(), 1);
+        assert_eq!(
+            response.additionals,
+            [Record {
+                name: "pi.hole".into(),
+                ty: QueryResponse::Unknown {
+                    ty: 65280,
+                    data: vec![1, 2, 3]
+                },
+                class: ClassType::IN,
+                ttl: 14,
+                data: vec![1, 2, 3]
+            }]
+        );
+        assert_eq!(
+            QueryType::from(&response.additionals[0].ty),
+            QueryType::Unknown(65280)
+        );
+        assert_eq!(response.additionals[0].data(), "\\# 3 010203");
+    }
+
+    #[test]
+    fn test_display_record() {
+        let record = Record {
+            name: "example.com".into(),
+            ty: QueryResponse::Mx {
+                preference: 10,
+                exchange: "mail.example.com".into(),
+            },
+            class: ClassType::IN,
+            ttl: 300,
+            data: vec![],
+        };
+        assert_eq!(record.ty.to_string(), "10 mail.example.com");
+        assert_eq!(
+            record.to_string(),
+            "example.com 300 IN MX 10 mail.example.com"
+        );
+
+        let record = Record {
+            name: "".into(),
+            ty: QueryResponse::Aaaa(Ipv6Addr::LOCALHOST),
+            class: ClassType::IN,
+            ttl: 60,
+            data: vec![],
+        };
+        assert_eq!(record.to_string(), ". 60 IN AAAA ::1");
+    }
+
+    /// Every seed of the fuzzing corpus, with each of its truncations and each single-byte
+    /// corruption, must parse or fail gracefully
+    #[test]
+    fn test_parse_bytes_corpus() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/parse_bytes");
+        let mut seeds = 0;
+        for entry in std::fs::read_dir(corpus).unwrap() {
+            let seed = std::fs::read(entry.unwrap().path()).unwrap();
+            assert!(parse_bytes(&seed).is_ok());
+            for len in 0..seed.len() {
+                let _ = parse_bytes(&seed[..len]);
+            }
+            for i in 0..seed.len() {
+                for byte in [0x00, 0x3f, 0x7f, 0xc0, 0xff] {
+                    let mut corrupted = seed.clone();
+                    corrupted[i] = byte;
+                    let _ = parse_bytes(&corrupted);
+                }
+            }
+            seeds += 1;
+        }
+        assert!(seeds > 0);
+    }
+
+    #[test]
+    fn test_cookie() {
+        let builder = QueryBuilder::new().cookie([1; 8], &[]);
+        let query = Response::parse(&builder.build("example.com", QueryType::A, 1)).unwrap();
+        assert_eq!(
+            query.edns_options().collect::<Vec<_>>(),
+            [&EdnsOption::Cookie {
+                client: [1; 8],
+                server: vec![],
+            }]
+        );
+        assert_eq!(query.server_cookie(), None);
+
+        let response = |options| Response {
+            header: Header::default(),
+            questions: vec![],
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![Record::new(
+                "",
+                QueryResponse::Opt {
+                    payload_size: 1232,
+                    options,
+                },
+                ClassType::default(),
+                0,
+            )],
+        };
+        let echoed = response(vec![EdnsOption::Cookie {
+            client: [1; 8],
+            server: vec![2; 16],
+        }]);
+        assert!(echoed.verify_cookie([1; 8]).is_ok());
+        assert_eq!(echoed.server_cookie(), Some(&[2; 16][..]));
+        assert!(matches!(
+            echoed.verify_cookie([3; 8]),
+            Err(DnsError::BadCookie)
+        ));
+        // too short to hold a server cookie
+        let malformed = response(vec![EdnsOption::Unknown {
+            code: 10,
+            data: vec![1; 12],
+        }]);
+        assert!(matches!(
+            malformed.verify_cookie([1; 8]),
+            Err(DnsError::BadCookie)
+        ));
+        assert!(response(vec![]).verify_cookie([1; 8]).is_ok());
+    }
+
+    #[test]
+    fn test_to_dig_format() {
+        let response = Response {
+            header: Header {
+                id: 0x1234,
+                flags: 0x81a0,
+                num_questions: 1,
+                num_answers: 1,
+                num_authorities: 0,
+                num_additionals: 1,
+            },
+            questions: vec![Question::new("example.com", QueryType::A, ClassType::IN)],
+            answers: vec![Record::new(
+                "example.com",
+                QueryResponse::a([192, 0, 2, 1]),
+                ClassType::IN,
+                300,
+            )],
+            authorities: vec![],
+            additionals: vec![Record {
+                name: "".into(),
+                ty: QueryResponse::Opt {
+                    payload_size: 1232,
+                    options: vec![],
+                },
+                class: ClassType::default(),
+                ttl: EDNS_FLAG_DO,
+                data: vec![],
+            }],
+        };
+        assert_eq!(
+            response.to_dig_format(),
+            ";; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4660\n\
+             ;; flags: qr rd ra ad; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1\n\
+             \n\
+             ;; OPT PSEUDOSECTION:\n\
+             ; EDNS: version: 0, flags: do; udp: 1232\n\
+             \n\
+             ;; QUESTION SECTION:\n\
+             ;example.com.\t\tIN\tA\n\
+             \n\
+             ;; ANSWER SECTION:\n\
+             example.com.\t300\tIN\tA\t192.0.2.1\n"
+        );
+    }
+
+    #[test]
+    fn test_to_zone_file() {
+        let record = |name: &str, ty, data: &[u8]| Record {
+            name: name.into(),
+            ty,
+            class: ClassType::IN,
+            ttl: 300,
+            data: data.to_vec(),
+        };
+        let response = Response {
+            answers: vec![
+                record(
+                    "example.com",
+                    QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+                    &[192, 0, 2, 1],
+                ),
+                record(
+                    "example.com",
+                    QueryResponse::Mx {
+                        preference: 10,
+                        exchange: "mail.example.com".into(),
+                    },
+                    &[],
+                ),
+                record(
+                    "example.com",
+                    QueryResponse::Txt("\x05hello\x09say \"hi\"".into()),
+                    b"\x05hello\x08say \"hi\"",
+                ),
+                record(
+                    "example.com",
+                    QueryResponse::Unknown {
+                        ty: 65280,
+                        data: vec![1, 2],
+                    },
+                    &[1, 2],
+                ),
+            ],
+            authorities: vec![record(
+                "",
+                QueryResponse::Soa(Soa {
+                    mname: "a.root-servers.net".into(),
+                    rname: "nstld.verisign-grs.com".into(),
+                    serial: 2023060100,
+                    refresh: 1800,
+                    retry: 900,
+                    expire: 604800,
+                    minimum: 86400,
+                }),
+                &[],
+            )],
+            header: Header::default(),
+            questions: vec![],
+            additionals: vec![],
+        };
+        assert_eq!(
+            response.to_zone_file(),
+            "example.com. 300 IN A 192.0.2.1\n\
+             example.com. 300 IN MX 10 mail.example.com.\n\
+             example.com. 300 IN TXT \"hello\" \"say \\\"hi\\\"\"\n\
+             example.com. 300 IN TYPE65280 \\# 2 0102\n\
+             . 300 IN SOA a.root-servers.net. nstld.verisign-grs.com. 2023060100 1800 900 604800 86400\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_compressed_question() {
+        // the second question's name is compressed against the first at offset 12, and the
+        // answer's name against the second
+        let response = b"\x00\x01\x81\x80\x00\x02\x00\x01\x00\x00\x00\x00\x07example\x03com\x00\x00\x01\x00\x01\x03www\xc0\x0c\x00\x01\x00\x01\xc0\x1d\x00\x01\x00\x01\x00\x00\x0e\x10\x00\x04\xc0\x00\x02\x01";
+        let response = Response::parse(response).unwrap();
+        let questions: Vec<_> = response.questions().map(Question::name).collect();
+        assert_eq!(questions, ["example.com", "www.example.com"]);
+        assert_eq!(response.questions[1].ty(), QueryType::A);
+        assert_eq!(response.questions[1].class(), ClassType::IN);
+        assert_eq!(
+            response.answers,
+            [Record {
+                name: "www.example.com".into(),
+                ty: QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+                class: ClassType::IN,
+                ttl: 3600,
+                data: vec![192, 0, 2, 1]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_header_counts() {
+        let response = b"\x00\x01\x85\x80\x00\x01\x00\x01\x00\x00\x00\x01\x02\x70\x69\x04\x68\x6f\x6c\x65\x00\x00\x01\x00\x01\xc0\x0c\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\xc0\xa8\x02\x66\xc0\x0c\xff\x00\x00\x01\x00\x00\x00\x0e\x00\x03\x01\x02\x03";
+        let response = Response::parse(response).unwrap();
+        let header = response.header();
+        assert_eq!(header.num_questions(), 1);
+        assert_eq!(header.num_answers(), 1);
+        assert_eq!(header.num_authorities(), 0);
+        assert_eq!(header.num_additionals(), 1);
+        assert_eq!(header.num_answers() as usize, response.answers().count());
+    }
+
+    #[test]
+    fn test_assemble_query() {
+        let mut header = Header::new(0x1234, 0x0100);
+        header.set_num_questions(1);
+        let question = Question::new("example.com", QueryType::Mx, ClassType::IN);
+        let mut message = vec![];
+        header.as_bytes(&mut message);
+        question.as_bytes(&mut message);
+        let builder = QueryBuilder::new().recursion_desired(true);
+        assert_eq!(message, builder.build("example.com", QueryType::Mx, 0x1234));
+
+        header.set_id(7);
+        header.set_flags(0x0040);
+        header.set_num_additionals(3);
+        let mut tweaked = vec![];
+        header.as_bytes(&mut tweaked);
+        assert_eq!(tweaked, [0, 7, 0, 0x40, 0, 1, 0, 0, 0, 0, 0, 3]);
+        assert_eq!(header.id(), 7);
+    }
+
+    #[test]
+    fn test_address_constructors() {
+        assert_eq!(
+            QueryResponse::a([192, 0, 2, 1]),
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1))
+        );
+        let mut octets = [0; 16];
+        octets[..2].copy_from_slice(&[0x20, 0x01]);
+        octets[15] = 1;
+        assert_eq!(
+            QueryResponse::aaaa(octets),
+            QueryResponse::Aaaa("2001::1".parse().unwrap())
+        );
+        assert_eq!(
+            QueryResponse::from(Ipv4Addr::LOCALHOST),
+            QueryResponse::a([127, 0, 0, 1])
+        );
+        assert_eq!(
+            QueryResponse::from(IpAddr::from(Ipv6Addr::LOCALHOST)),
+            QueryResponse::Aaaa(Ipv6Addr::LOCALHOST)
+        );
+    }
+
+    #[test]
+    fn test_reserved_bits() {
+        let response = |flags| Response {
+            header: Header {
+                flags,
+                ..Header::default()
+            },
+            questions: vec![],
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+        };
+        // QR, RD, RA, AD and CD are all fine
+        let conforming = response(0x81b0);
+        assert_eq!(conforming.header().reserved_bits(), 0);
+        assert!(!conforming.has_protocol_anomalies());
+
+        let reserved = response(0x81c0);
+        assert_eq!(reserved.header().reserved_bits(), 0b100);
+        assert!(reserved.has_protocol_anomalies());
+
+        assert!(response(0x0100).has_protocol_anomalies());
+    }
+
+    #[test]
+    fn test_answers_for_ignores_case() {
+        let record = |name: &str, ty| Record {
+            name: name.into(),
+            ty,
+            class: ClassType::IN,
+            ttl: 60,
+            data: vec![],
+        };
+        let response = Response {
+            header: Header::default(),
+            questions: vec![],
+            answers: vec![
+                record(
+                    "www.example.test",
+                    QueryResponse::Cname("WEB.example.test".into()),
+                ),
+                record(
+                    "web.example.test.",
+                    QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+                ),
+                record("other.test", QueryResponse::A(Ipv4Addr::new(192, 0, 2, 2))),
+            ],
+            authorities: vec![],
+            additionals: vec![],
+        };
+        let answers: Vec<_> = response
+            .answers_for("WWW.Example.TEST", QueryType::A)
+            .map(Record::data)
+            .collect();
+        assert_eq!(answers, ["192.0.2.1"]);
+        assert!(names_equal("Example.COM.", "example.com"));
+        assert!(!names_equal("example.com", "example.org"));
+    }
+
+    #[test]
+    fn test_min_ttl() {
+        let record = |ttl| Record {
+            name: "example.com".into(),
+            ty: QueryResponse::A(Ipv4Addr::LOCALHOST),
+            class: ClassType::IN,
+            ttl,
+            data: vec![127, 0, 0, 1],
+        };
+        let response = Response {
+            header: Header::default(),
+            questions: vec![],
+            answers: vec![record(300), record(60), record(3600)],
+            authorities: vec![record(30)],
+            additionals: vec![],
+        };
+        assert_eq!(response.min_ttl(), Some(60));
+        assert_eq!(
+            response.min_ttl_in(&[Section::Answer, Section::Authority]),
+            Some(30)
+        );
+        assert_eq!(response.min_ttl_in(&[Section::Additional]), None);
+    }
+
+    #[test]
+    fn test_negative_ttl() {
+        let soa = |ttl, minimum| {
+            Record::new(
+                "example.com",
+                QueryResponse::Soa(Soa {
+                    mname: "ns.example.com".into(),
+                    rname: "admin.example.com".into(),
+                    serial: 1,
+                    refresh: 3600,
+                    retry: 900,
+                    expire: 604800,
+                    minimum,
+                }),
+                ClassType::IN,
+                ttl,
+            )
+        };
+        let response = |authorities| Response {
+            header: Header::default(),
+            questions: vec![],
+            answers: vec![],
+            authorities,
+            additionals: vec![],
+        };
+        assert_eq!(response(vec![soa(3600, 300)]).negative_ttl(), Some(300));
+        assert_eq!(response(vec![soa(60, 300)]).negative_ttl(), Some(60));
+        assert_eq!(response(vec![]).negative_ttl(), None);
+    }
+
+    #[test]
+    fn test_any_query() {
+        let query = QueryBuilder::new().build("example.com", QueryType::Any, 1);
+        assert!(query.ends_with(b"\x07example\x03com\x00\x00\xff\x00\x01"));
+        assert_eq!(QueryType::try_from(255).unwrap(), QueryType::Any);
+        assert_eq!(QueryType::from_str("ANY", true).unwrap(), QueryType::Any);
+
+        // ANY has no data of its own, so a record claiming to be of type ANY is rejected
+        let response = single_answer(255, b"");
+        assert!(Response::parse(&response).is_err());
+    }
+
+    #[test]
+    fn test_reject_impossible_counts() {
+        // claims 65535 answers, but holds a single A record
+        let mut response = single_answer(1, &[127, 0, 0, 1]);
+        response[6..8].copy_from_slice(&u16::MAX.to_be_bytes());
+        let err = Response::parse(&response).unwrap_err();
+        assert!(err.to_string().contains("65535 records"), "{err}");
+    }
+
+    #[test]
+    fn test_answers_of_type() {
+        let a = Record::new(
+            "www.example",
+            QueryResponse::A(Ipv4Addr::LOCALHOST),
+            ClassType::IN,
+            60,
+        );
+        let aaaa = Record::new(
+            "www.example",
+            QueryResponse::Aaaa(Ipv6Addr::LOCALHOST),
+            ClassType::IN,
+            60,
+        );
+        let response = Response {
+            header: Header::default(),
+            questions: vec![],
+            answers: vec![a.clone(), aaaa.clone()],
+            authorities: vec![],
+            additionals: vec![],
+        };
+        assert_eq!(
+            response.answers_of_type(QueryType::A).collect::<Vec<_>>(),
+            [&a]
+        );
+        assert_eq!(
+            response
+                .answers_of_type(QueryType::Aaaa)
+                .collect::<Vec<_>>(),
+            [&aaaa]
+        );
+        assert_eq!(response.answers_of_type(QueryType::Mx).count(), 0);
+        assert_eq!(response.answers_of_type(QueryType::Any).count(), 2);
+    }
+
+    #[test]
+    fn test_from_bytes_file() {
+        let path = std::env::temp_dir().join(format!("dns-query-{}.bin", std::process::id()));
+        std::fs::write(&path, single_answer(1, &[127, 0, 0, 1])).unwrap();
+        let response = Response::from_bytes_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            response.unwrap().answers().next().unwrap().ty,
+            QueryResponse::A(Ipv4Addr::LOCALHOST)
+        );
+        assert!(Response::from_bytes_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_first_address() {
+        let record = |ty| Record::new("www.example", ty, ClassType::IN, 60);
+        let response = |answers| Response {
+            header: Header::default(),
+            questions: vec![],
+            answers,
+            authorities: vec![],
+            additionals: vec![],
+        };
+        let cname = record(QueryResponse::Cname("example".into()));
+        let aaaa = record(QueryResponse::Aaaa(Ipv6Addr::LOCALHOST));
+        let a = record(QueryResponse::A(Ipv4Addr::LOCALHOST));
+        assert_eq!(
+            response(vec![cname.clone(), aaaa, a]).first_address(),
+            Some(IpAddr::from(Ipv6Addr::LOCALHOST))
+        );
+        assert_eq!(response(vec![cname]).first_address(), None);
+    }
+
+    #[test]
+    fn test_rdata_too_long() {
+        // the A record claims 16 bytes of rdata, but the message ends after 4
+        let mut response = single_answer(1, &[127, 0, 0, 1]);
+        let len = response.len();
+        response[len - 6..len - 4].copy_from_slice(&16u16.to_be_bytes());
+        let err = Response::parse(&response).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ParseError>(),
+            Some(ParseError::RdataTooLong {
+                claimed: 16,
+                available: 4
+            })
+        ));
+        assert_eq!(
+            parse_bytes(&response).unwrap_err(),
+            ParseError::RdataTooLong {
+                claimed: 16,
+                available: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_from() {
+        let message = single_answer(1, &[127, 0, 0, 1]);
+        let mut stream = vec![];
+        crate::write_tcp_message(&mut stream, &message).unwrap();
+        crate::write_tcp_message(&mut stream, &message).unwrap();
+
+        let mut reader = stream.as_slice();
+        for _ in 0..2 {
+            let response = Response::parse_from(&mut reader).unwrap();
+            assert_eq!(response.answers().count(), 1);
+        }
+        assert!(Response::parse_from(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_extended_response_code() {
+        let opt = |ttl| Record {
+            name: "".into(),
+            ty: QueryResponse::Opt {
+                payload_size: 1232,
+                options: vec![],
+            },
+            class: ClassType::IN,
+            ttl,
+            data: vec![],
+        };
+        let response = |rcode, additionals| Response {
+            header: Header {
+                flags: FLAG_QR | rcode,
+                ..Default::default()
+            },
+            questions: vec![],
+            answers: vec![],
+            authorities: vec![],
+            additionals,
+        };
+
+        // BADVERS is 16: an extended RCODE of 1, with 0 in the header
+        let badvers = response(0, vec![opt(1 << 24)]);
+        assert_eq!(badvers.header().response_code(), ResponseCode::NoError);
+        assert_eq!(badvers.response_code(), ResponseCode::BadVers);
+        assert_eq!(
+            badvers.classify(),
+            ResponseKind::Error(ResponseCode::BadVers)
+        );
+
+        assert_eq!(
+            response(3, vec![opt(0)]).response_code(),
+            ResponseCode::NxDomain
+        );
+        assert_eq!(response(3, vec![]).response_code(), ResponseCode::NxDomain);
+        assert_eq!(
+            response(1, vec![opt(0xff << 24)]).response_code(),
+            ResponseCode::Other(0xff1)
+        );
+    }
+
+    #[test]
+    fn test_raw_rdata_keeps_compression() {
+        // the NS record's target is compressed to a pointer back to the question name
+        let response = b"\x00\x01\x81\x80\x00\x01\x00\x01\x00\x00\x00\x00\x02\x70\x69\x04\x68\x6f\x6c\x65\x00\x00\x02\x00\x01\xc0\x0c\x00\x02\x00\x01\x00\x00\x00\x0e\x00\x02\xc0\x0c";
+        let response = Response::parse(response).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(record.ty, QueryResponse::Ns("pi.hole".into()));
+        assert_eq!(record.raw_rdata(), b"\xc0\x0c");
+    }
+
+    #[test]
+    fn test_classify() {
+        let record = |name: &str, ty| Record {
+            name: name.into(),
+            ty,
+            class: ClassType::IN,
+            ttl: 60,
+            data: vec![],
+        };
+        let response = |flags, answers, authorities| Response {
+            header: Header {
+                flags,
+                ..Default::default()
+            },
+            questions: vec![],
+            answers,
+            authorities,
+            additionals: vec![],
+        };
+        let a = record("example.com", QueryResponse::A(Ipv4Addr::LOCALHOST));
+        let ns = record("com", QueryResponse::Ns("a.gtld-servers.net".into()));
+
+        assert_eq!(
+            response(0x8180, vec![a], vec![]).classify(),
+            ResponseKind::Answer
+        );
+        assert_eq!(
+            response(0x8000, vec![], vec![ns.clone()]).classify(),
+            ResponseKind::Referral {
+                ns: vec!["a.gtld-servers.net".into()]
+            }
+        );
+        assert_eq!(
+            response(0x8400, vec![], vec![]).classify(),
+            ResponseKind::NoData
+        );
+        assert_eq!(
+            response(0x8403, vec![], vec![ns]).classify(),
+            ResponseKind::Error(ResponseCode::NxDomain)
+        );
+    }
+
+    #[test]
+    fn test_verify_question_class() {
+        let response = b"\x00\x01\x85\x80\x00\x01\x00\x01\x00\x00\x00\x00\x02\x70\x69\x04\x68\x6f\x6c\x65\x00\x00\x01\x00\x01\xc0\x0c\x00\x01\x00\x01\x00\x00\x00\x00\x00\x04\xc0\xa8\x02\x66";
+        let response = Response::parse(response).unwrap();
+
+        assert!(response
+            .verify_question(&Question::new("pi.hole", QueryType::A, ClassType::IN))
+            .is_ok());
+        assert!(matches!(
+            response.verify_question(&Question::new("pi.hole", QueryType::A, ClassType::CH)),
+            Err(DnsError::QuestionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_edns_options() {
+        let response = b"\x00\x01\x85\x80\x00\x01\x00\x00\x00\x00\x00\x01\x02\x70\x69\x04\x68\x6f\x6c\x65\x00\x00\x01\x00\x01\x00\x00\x29\x10\x00\x00\x00\x00\x00\x00\x13\xfd\xe9\x00\x03\xaa\xbb\xcc\x00\x0a\x00\x08\x01\x02\x03\x04\x05\x06\x07\x08";
+        let response = Response::parse(response).unwrap();
+
+        let record = response.opt().unwrap();
+        assert!(matches!(
+            record.ty,
+            QueryResponse::Opt {
+                payload_size: 4096,
+                ..
+            }
+        ));
+        assert_eq!(
+            response.edns_options().collect::<Vec<_>>(),
+            [
+                &EdnsOption::Unknown {
+                    code: 65001,
+                    data: vec![0xaa, 0xbb, 0xcc]
+                },
+                &EdnsOption::Cookie {
+                    client: [1, 2, 3, 4, 5, 6, 7, 8],
+                    server: vec![]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_hinfo() {
+        let input = b"\x02pi\x00\x00\x0d\x00\x01\x00\x00\x00\x3c\x00\x0b\x05ARM64\x04RISC";
+        let (_, record) = Record::parse(input, input, true).unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Hinfo {
+                cpu: "ARM64".into(),
+                os: "RISC".into()
+            }
+        );
+        assert_eq!(record.data(), "ARM64 RISC");
+    }
+
+    #[test]
+    fn test_in_bailiwick() {
+        assert!(in_bailiwick("ns.example", "example"));
+        assert!(in_bailiwick("EXAMPLE.", "example"));
+        assert!(in_bailiwick("victim.com", ""));
+        assert!(!in_bailiwick("victim.com", "example"));
+        assert!(!in_bailiwick("badexample", "example"));
+    }
+
+    #[test]
+    fn test_discard_out_of_bailiwick() {
+        let glue = Record {
+            name: "ns.example".into(),
+            ty: QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+            data: vec![192, 0, 2, 1],
+            ..Default::default()
+        };
+        let poison = Record {
+            name: "victim.com".into(),
+            ty: QueryResponse::A(Ipv4Addr::new(203, 0, 113, 66)),
+            data: vec![203, 0, 113, 66],
+            ..Default::default()
+        };
+        let mut input = b"\x00\x01\x81\x00\x00\x01\x00\x00\x00\x00\x00\x02".to_vec();
+        Question::new("www.example", QueryType::A, ClassType::IN).as_bytes(&mut input);
+        poison.as_bytes(&mut input);
+        glue.as_bytes(&mut input);
+        let mut response = Response::parse(&input).unwrap();
+
+        assert_eq!(response.discard_out_of_bailiwick("example"), [poison]);
+        assert_eq!(response.additionals, [glue]);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let record = |octet: u8, ttl: u32| Record {
+            name: "example".into(),
+            ty: QueryResponse::A(Ipv4Addr::new(192, 0, 2, octet)),
+            ttl,
+            data: vec![192, 0, 2, octet],
+            ..Default::default()
+        };
+        let response = |records: &[Record]| {
+            let mut input = b"\x00\x01\x81\x00\x00\x01\x00\x02\x00\x00\x00\x00".to_vec();
+            Question::new("example", QueryType::A, ClassType::IN).as_bytes(&mut input);
+            records
+                .iter()
+                .for_each(|record| record.as_bytes(&mut input));
+            Response::parse(&input).unwrap()
+        };
+
+        let original = response(&[record(1, 60), record(2, 60)]);
+        let reordered = response(&[record(2, 300), record(1, 10)]);
+        let changed = response(&[record(1, 60), record(3, 60)]);
+        assert_eq!(original.fingerprint(), reordered.fingerprint());
+        assert_ne!(original.fingerprint(), changed.fingerprint());
+    }
+
+    #[test]
+    fn test_cname_coexistence() {
+        let answer = |name: &str, ty: QueryResponse| Record {
+            name: name.into(),
+            ty,
+            class: ClassType::IN,
+            ttl: 60,
+            data: vec![],
+        };
+        let response = |answers: Vec<Record>| Response {
+            header: Header::default(),
+            questions: vec![],
+            answers,
+            authorities: vec![],
+            additionals: vec![],
+        };
+
+        let broken = response(vec![
+            answer("www.test", QueryResponse::Cname("web.test".into())),
+            answer("WWW.test", QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1))),
+        ]);
+        assert!(broken.has_cname_coexistence_error());
+
+        let chain = response(vec![
+            answer("www.test", QueryResponse::Cname("web.test".into())),
+            answer(
+                "www.test",
+                QueryResponse::Unknown {
+                    ty: 47,
+                    data: vec![],
+                },
+            ),
+            answer("web.test", QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1))),
+        ]);
+        assert!(!chain.has_cname_coexistence_error());
+    }
+
+    #[test]
+    fn test_extract_addresses() {
+        let answer = |ty: QueryResponse, data: &[u8]| Record {
+            name: "mixed.test".into(),
+            ty,
+            class: ClassType::IN,
+            ttl: 60,
+            data: data.to_vec(),
+        };
+        let mut input = b"\x00\x01\x81\x80\x00\x00\x00\x03\x00\x00\x00\x00".to_vec();
+        answer(
+            QueryResponse::Cname("other.test".into()),
+            &encode_dns_name("other.test"),
+        )
+        .as_bytes(&mut input);
+        answer(
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+            &[192, 0, 2, 1],
+        )
+        .as_bytes(&mut input);
+        let v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        answer(QueryResponse::Aaaa(v6), &v6.octets()).as_bytes(&mut input);
+
+        let response = Response::parse(&input).unwrap();
+        assert_eq!(
+            response.extract_addresses(),
+            [IpAddr::from(Ipv4Addr::new(192, 0, 2, 1)), IpAddr::from(v6)]
+        );
+    }
+
+    #[test]
+    fn test_parse_naptr() {
+        let rdata = b"\x00\x64\x00\x0a\x01S\x07SIP+D2U\x00\x04_sip\x04_udp\x07example\x03com\x00";
+        let response = Response::parse(&single_answer(35, rdata)).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Naptr {
+                order: 100,
+                preference: 10,
+                flags: "S".into(),
+                services: "SIP+D2U".into(),
+                regexp: "".into(),
+                replacement: "_sip._udp.example.com".into(),
+            }
+        );
+        assert_eq!(
+            record.data(),
+            r#"100 10 "S" "SIP+D2U" "" _sip._udp.example.com"#
+        );
+
+        let rdata = b"\x00\x64\x00\x0a\x01U\x07E2U+sip\x1b!^.*$!sip:info@example.com!\x00";
+        let response = Response::parse(&single_answer(35, rdata)).unwrap();
+        assert_eq!(
+            response.answers().next().unwrap().data(),
+            r#"100 10 "U" "E2U+sip" "!^.*$!sip:info@example.com!" ."#
+        );
+    }
+
+    #[test]
+    fn test_parse_dnskey() {
+        let input =
+            b"\x07example\x00\x00\x30\x00\x01\x00\x00\x0e\x10\x00\x07\x01\x00\x03\x0d\x01\x02\x03";
+        let (_, record) = Record::parse(input, input, true).unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Dnskey(Dnskey {
+                flags: 256,
+                protocol: 3,
+                algorithm: 13,
+                public_key: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(record.data(), "256 3 13 AQID");
+    }
+
+    #[test]
+    fn test_parse_rrsig() {
+        let mut rdata = b"\x00\x01\x0d\x02\x00\x00\x0e\x10".to_vec();
+        // 2024-01-31 12:00:00 and 2024-01-01 00:00:00
+        rdata.extend(1706702400u32.to_be_bytes());
+        rdata.extend(1704067200u32.to_be_bytes());
+        rdata.extend(b"\x30\x39\x07example\x03com\x00\x01\x02\x03");
+        let response = Response::parse(&single_answer(46, &rdata)).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Rrsig(Rrsig {
+                type_covered: QueryType::A,
+                algorithm: 13,
+                labels: 2,
+                original_ttl: 3600,
+                expiration: 1706702400,
+                inception: 1704067200,
+                key_tag: 12345,
+                signer_name: "example.com".into(),
+                signature: vec![1, 2, 3],
+            })
+        );
+        assert_eq!(
+            record.data(),
+            "A 13 2 3600 20240131120000 20240101000000 12345 example.com AQID"
+        );
+    }
+
+    #[test]
+    fn test_parse_cds() {
+        let mut input =
+            b"\x07example\x00\x00\x3b\x00\x01\x00\x00\x0e\x10\x00\x24\x30\x39\x0d\x02".to_vec();
+        input.extend([0xde, 0xad, 0xbe, 0xef].repeat(8));
+        let (_, record) = Record::parse(&input, &input, true).unwrap();
+        let QueryResponse::Cds(ref ds) = record.ty else {
+            panic!("expected a CDS record, got {:?}", record.ty);
+        };
+        assert!(!ds.is_delete());
+        assert_eq!(
+            record.data(),
+            format!("12345 13 2 {}", "DEADBEEF".repeat(8))
+        );
+
+        let input = b"\x07example\x00\x00\x3b\x00\x01\x00\x00\x0e\x10\x00\x05\x00\x00\x00\x00\x00";
+        let (_, record) = Record::parse(input, input, true).unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Cds(Ds {
+                key_tag: 0,
+                algorithm: 0,
+                digest_type: 0,
+                digest: vec![0]
+            })
+        );
+        assert!(matches!(record.ty, QueryResponse::Cds(ref ds) if ds.is_delete()));
+        assert_eq!(record.data(), "0 0 0 00");
+    }
+
+    #[test]
+    fn test_parse_tlsa_and_openpgpkey() {
+        let response =
+            Response::parse(&single_answer(52, b"\x03\x01\x01\xde\xad\xbe\xef")).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Tlsa {
+                usage: 3,
+                selector: 1,
+                matching_type: 1,
+                cert_data: vec![0xde, 0xad, 0xbe, 0xef],
+            }
+        );
+        assert_eq!(record.data(), "3 1 1 DEADBEEF");
+        assert!(Response::parse(&single_answer(52, b"\x03\x01")).is_err());
+
+        let response = Response::parse(&single_answer(61, b"\x99\x01\x0d")).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(record.ty, QueryResponse::Openpgpkey(vec![0x99, 0x01, 0x0d]));
+        assert_eq!(record.data(), "99010D");
+    }
+
+    #[test]
+    fn test_parse_cdnskey() {
+        let input =
+            b"\x07example\x00\x00\x3c\x00\x01\x00\x00\x0e\x10\x00\x07\x01\x01\x03\x0d\x01\x02\x03";
+        let (_, record) = Record::parse(input, input, true).unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Cdnskey(Dnskey {
+                flags: 257,
+                protocol: 3,
+                algorithm: 13,
+                public_key: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(record.data(), "257 3 13 AQID");
+
+        let input = b"\x07example\x00\x00\x3c\x00\x01\x00\x00\x0e\x10\x00\x05\x00\x00\x03\x00\x00";
+        let (_, record) = Record::parse(input, input, true).unwrap();
+        assert!(matches!(record.ty, QueryResponse::Cdnskey(ref key) if key.is_delete()));
+        assert_eq!(record.data(), "0 3 0 AA==");
+    }
+
+    /// A response holding a single answer for the root name, with the given type and rdata
+    fn single_answer(ty: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut response = b"\x00\x01\x81\x80\x00\x00\x00\x01\x00\x00\x00\x00\x00".to_vec();
+        response.extend(ty.to_be_bytes());
+        response.extend(b"\x00\x01\x00\x00\x0e\x10");
+        response.extend((rdata.len() as u16).to_be_bytes());
+        response.extend(rdata);
+        response
+    }
+
+    #[test]
+    fn test_parse_wks() {
+        // TCP on 192.0.2.1, with the bits for ports 25 and 80 set
+        let rdata = [192, 0, 2, 1, 6, 0, 0, 0, 0x40, 0, 0, 0, 0, 0, 0, 0x80];
+        let response = Response::parse(&single_answer(11, &rdata)).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Wks {
+                address: Ipv4Addr::new(192, 0, 2, 1),
+                protocol: 6,
+                ports: vec![25, 80],
+            }
+        );
+        assert_eq!(record.data(), "192.0.2.1 6 25 80");
+    }
+
+    #[test]
+    fn test_record_new() {
+        let wks = QueryResponse::Wks {
+            address: Ipv4Addr::new(192, 0, 2, 1),
+            protocol: 6,
+            ports: vec![25, 80],
+        };
+        let record = Record::new("example", wks, ClassType::IN, 60);
+        assert_eq!(
+            record.data,
+            [192, 0, 2, 1, 6, 0, 0, 0, 0x40, 0, 0, 0, 0, 0, 0, 0x80]
+        );
+
+        let types = [
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+            QueryResponse::Mx {
+                preference: 10,
+                exchange: "mail.example".into(),
+            },
+            QueryResponse::Soa(Soa {
+                mname: "ns.example".into(),
+                rname: "admin.example".into(),
+                serial: 1,
+                refresh: 3600,
+                retry: 900,
+                expire: 604800,
+                minimum: 60,
+            }),
+            QueryResponse::Hinfo {
+                cpu: "x86".into(),
+                os: "linux".into(),
+            },
+            QueryResponse::Naptr {
+                order: 100,
+                preference: 10,
+                flags: "u".into(),
+                services: "E2U+sip".into(),
+                regexp: "!^.*$!sip:info@example!".into(),
+                replacement: "".into(),
+            },
+            QueryResponse::Dnskey(Dnskey {
+                flags: 257,
+                protocol: 3,
+                algorithm: 13,
+                public_key: vec![1, 2, 3, 4],
+            }),
+        ];
+        for ty in types {
+            let record = Record::new("", ty.clone(), ClassType::IN, 3600);
+            let query_type = u16::from(QueryType::from(&ty));
+            let response = Response::parse(&single_answer(query_type, &record.data)).unwrap();
+            assert_eq!(response.answers().next().unwrap().ty, ty);
+        }
+    }
+
+    #[test]
+    fn test_parse_mailbox_names() {
+        let rdata = encode_dns_name("host.example");
+        for (code, ty) in [
+            (7, QueryResponse::Mb("host.example".into())),
+            (8, QueryResponse::Mg("host.example".into())),
+            (9, QueryResponse::Mr("host.example".into())),
+        ] {
+            let response = Response::parse(&single_answer(code, &rdata)).unwrap();
+            let record = response.answers().next().unwrap();
+            assert_eq!(record.ty, ty);
+            assert_eq!(record.data(), "host.example");
+            assert_eq!(Record::new("", ty, ClassType::IN, 60).data, rdata);
+        }
+    }
+
+    #[test]
+    fn test_parse_null() {
+        let rdata = [0xde, 0xad, 0x00, 0xbe, 0xef];
+        let response = Response::parse(&single_answer(10, &rdata)).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(record.ty, QueryResponse::Null(rdata.to_vec()));
+        assert_eq!(record.data(), "dead00beef");
+        assert_eq!(record.to_zone_line(), ". 3600 IN NULL \\# 5 dead00beef");
+        assert_eq!(
+            Record::new("", record.ty.clone(), ClassType::IN, 60).data,
+            rdata
+        );
+    }
+
+    #[test]
+    fn test_parse_minfo() {
+        let mut rdata = encode_dns_name("owner.example");
+        rdata.extend(encode_dns_name("errors.example"));
+        let response = Response::parse(&single_answer(14, &rdata)).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Minfo {
+                rmailbx: "owner.example".into(),
+                emailbx: "errors.example".into(),
+            }
+        );
+        assert_eq!(record.data(), "owner.example errors.example");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_record() {
+        let records = [
+            Record::new(
+                "example",
+                QueryResponse::Mx {
+                    preference: 10,
+                    exchange: "mail.example".into(),
+                },
+                ClassType::IN,
+                3600,
+            ),
+            Record::new(
+                "",
+                QueryResponse::Opt {
+                    payload_size: 1232,
+                    options: vec![EdnsOption::Cookie {
+                        client: [1; 8],
+                        server: vec![],
+                    }],
+                },
+                ClassType::IN,
+                0,
+            ),
+        ];
+        for record in records {
+            let json = serde_json::to_string(&record).unwrap();
+            assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+        }
+
+        // the rdata is rebuilt from the type, whatever the serialized data says
+        let json = r#"{"name":"example","ty":{"A":"192.0.2.1"},"class":"IN","ttl":60,"data":[]}"#;
+        let record: Record = serde_json::from_str(json).unwrap();
+        assert_eq!(record.data, [192, 0, 2, 1]);
+    }
+
+    #[test]
+    fn test_validate_records() {
+        let valid = [
+            (1, b"\x7f\x00\x00\x01".to_vec()),
+            (28, [0; 16].to_vec()),
+            (
+                6,
+                b"\x02ns\x00\x05admin\x00\x00\x00\x00\x01\x00\x00\x0e\x10\x00\x00\x03\x84\x00\x09\x3a\x80\x00\x00\x00\x3c".to_vec(),
+            ),
+            (15, b"\x00\x0a\x04mail\x00".to_vec()),
+            (15, b"\x00\x00\x00".to_vec()),
+        ];
+        for (ty, rdata) in valid {
+            Response::parse(&single_answer(ty, &rdata))
+                .unwrap_or_else(|e| panic!("type {ty} should be valid: {e:?}"));
+        }
+
+        let invalid = [
+            (1, b"\x7f\x00\x01".to_vec()),
+            (28, [0; 4].to_vec()),
+            (
+                6,
+                b"\x00\x05admin\x00\x00\x00\x00\x01\x00\x00\x0e\x10\x00\x00\x03\x84\x00\x09\x3a\x80\x00\x00\x00\x3c".to_vec(),
+            ),
+            (15, b"\x00\x0a\x00".to_vec()),
+            (59, b"\x30\x39\x0d\x02\xde\xad\xbe\xef".to_vec()),
+        ];
+        for (ty, rdata) in invalid {
+            assert!(
+                Response::parse(&single_answer(ty, &rdata)).is_err(),
+                "type {ty} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let response = Response::parse_lenient(&single_answer(1, b"\x7f\x00\x01")).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Unknown {
+                ty: 1,
+                data: b"\x7f\x00\x01".to_vec()
+            }
+        );
+
+        // an MX record with an empty exchange is rejected by `parse`, but kept here
+        let mx = single_answer(15, b"\x00\x0a\x00");
+        assert!(Response::parse(&mx).is_err());
+        let response = Response::parse_lenient(&mx).unwrap();
+        assert_eq!(
+            response.answers().next().unwrap().ty,
+            QueryResponse::Mx {
+                preference: 10,
+                exchange: String::new()
+            }
+        );
+    }
+
+    #[cfg(feature = "extensible")]
+    #[test]
+    fn test_registered_parser() {
+        // a type number reserved for private use, which no other test parses, as the registry
+        // is shared by the whole process
+        const PRIVATE: u16 = 65534;
+        QueryType::register_parser(PRIVATE, |rdata, _| Ok(format!("{} bytes", rdata.len())));
+
+        let response = Response::parse(&single_answer(PRIVATE, b"abc")).unwrap();
+        let record = response.answers().next().unwrap();
+        assert_eq!(
+            record.ty,
+            QueryResponse::Custom {
+                ty: PRIVATE,
+                data: "3 bytes".into()
+            }
+        );
+        assert_eq!(record.data(), "3 bytes");
+
+        // types without a parser are still kept as unknown
+        let response = Response::parse(&single_answer(PRIVATE + 1, b"abc")).unwrap();
+        assert!(matches!(
+            response.answers().next().unwrap().ty,
+            QueryResponse::Unknown { .. }
+        ));
+    }
 }
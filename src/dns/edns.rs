@@ -0,0 +1,169 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use winnow::{binary::be_u16, multi::length_data, IResult, Parser};
+
+use super::{to_hex, AsBytes};
+
+const COOKIE: u16 = 10;
+const CLIENT_SUBNET: u16 = 8;
+const TCP_KEEPALIVE: u16 = 11;
+const PADDING: u16 = 12;
+const EXTENDED_ERROR: u16 = 15;
+
+/// An option carried in the rdata of an OPT pseudo-record, as defined by [RFC 6891 section
+/// 6.1.2](https://datatracker.ietf.org/doc/html/rfc6891#section-6.1.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdnsOption {
+    /// DNS cookie ([RFC 7873](https://datatracker.ietf.org/doc/html/rfc7873))
+    Cookie { client: [u8; 8], server: Vec<u8> },
+
+    /// EDNS client subnet ([RFC 7871](https://datatracker.ietf.org/doc/html/rfc7871))
+    ClientSubnet {
+        family: u16,
+        source_prefix_length: u8,
+        scope_prefix_length: u8,
+        address: Vec<u8>,
+    },
+
+    /// edns-tcp-keepalive ([RFC 7828](https://datatracker.ietf.org/doc/html/rfc7828)), with
+    /// the timeout in units of 100 milliseconds
+    TcpKeepalive(Option<u16>),
+
+    /// padding ([RFC 7830](https://datatracker.ietf.org/doc/html/rfc7830)), holding the number
+    /// of padding bytes
+    Padding(usize),
+
+    /// extended DNS error ([RFC 8914](https://datatracker.ietf.org/doc/html/rfc8914))
+    ExtendedError { info_code: u16, extra_text: String },
+
+    /// an option this crate doesn't interpret, preserved as-is
+    Unknown { code: u16, data: Vec<u8> },
+}
+
+impl EdnsOption {
+    pub(crate) fn parse(input: &[u8]) -> IResult<&[u8], Self> {
+        (be_u16, length_data(be_u16))
+            .map(|(code, data)| Self::from_parts(code, data))
+            .parse_next(input)
+    }
+
+    /// Interpret the data of an option.  Options whose data is malformed for their code are kept
+    /// as `Unknown` rather than rejected.
+    fn from_parts(code: u16, data: &[u8]) -> Self {
+        match (code, data.len()) {
+            (COOKIE, 8) | (COOKIE, 16..=40) => Self::Cookie {
+                client: data[..8].try_into().unwrap(),
+                server: data[8..].to_vec(),
+            },
+            (CLIENT_SUBNET, 4..) => Self::ClientSubnet {
+                family: u16::from_be_bytes([data[0], data[1]]),
+                source_prefix_length: data[2],
+                scope_prefix_length: data[3],
+                address: data[4..].to_vec(),
+            },
+            (TCP_KEEPALIVE, 0) => Self::TcpKeepalive(None),
+            (TCP_KEEPALIVE, 2) => Self::TcpKeepalive(Some(u16::from_be_bytes([data[0], data[1]]))),
+            (PADDING, len) => Self::Padding(len),
+            (EXTENDED_ERROR, 2..) => Self::ExtendedError {
+                info_code: u16::from_be_bytes([data[0], data[1]]),
+                extra_text: String::from_utf8_lossy(&data[2..]).to_string(),
+            },
+            _ => Self::Unknown {
+                code,
+                data: data.to_vec(),
+            },
+        }
+    }
+
+    /// The option code of this option
+    pub fn code(&self) -> u16 {
+        match self {
+            EdnsOption::Cookie { .. } => COOKIE,
+            EdnsOption::ClientSubnet { .. } => CLIENT_SUBNET,
+            EdnsOption::TcpKeepalive(_) => TCP_KEEPALIVE,
+            EdnsOption::Padding(_) => PADDING,
+            EdnsOption::ExtendedError { .. } => EXTENDED_ERROR,
+            EdnsOption::Unknown { code, .. } => *code,
+        }
+    }
+}
+
+impl AsBytes for EdnsOption {
+    fn as_bytes<T>(&self, dest: &mut T)
+    where
+        T: std::io::Write,
+    {
+        let data = match self {
+            EdnsOption::Cookie { client, server } => [client.as_slice(), server].concat(),
+            EdnsOption::ClientSubnet {
+                family,
+                source_prefix_length,
+                scope_prefix_length,
+                address,
+            } => {
+                let mut data = family.to_be_bytes().to_vec();
+                data.extend([*source_prefix_length, *scope_prefix_length]);
+                data.extend(address);
+                data
+            }
+            EdnsOption::TcpKeepalive(timeout) => {
+                timeout.map_or(vec![], |timeout| timeout.to_be_bytes().to_vec())
+            }
+            EdnsOption::Padding(len) => vec![0; *len],
+            EdnsOption::ExtendedError {
+                info_code,
+                extra_text,
+            } => [&info_code.to_be_bytes()[..], extra_text.as_bytes()].concat(),
+            EdnsOption::Unknown { data, .. } => data.clone(),
+        };
+        let _ = dest.write_all(&self.code().to_be_bytes());
+        let _ = dest.write_all(&(data.len() as u16).to_be_bytes());
+        let _ = dest.write_all(&data);
+    }
+}
+
+impl Display for EdnsOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdnsOption::Cookie { client, server } => {
+                write!(f, "COOKIE: {}{}", to_hex(client), to_hex(server))
+            }
+            EdnsOption::ClientSubnet {
+                family,
+                source_prefix_length,
+                scope_prefix_length,
+                address,
+            } => {
+                let address = match family {
+                    1 => {
+                        let mut octets = [0u8; 4];
+                        octets.iter_mut().zip(address).for_each(|(o, a)| *o = *a);
+                        IpAddr::from(Ipv4Addr::from(octets)).to_string()
+                    }
+                    2 => {
+                        let mut octets = [0u8; 16];
+                        octets.iter_mut().zip(address).for_each(|(o, a)| *o = *a);
+                        IpAddr::from(Ipv6Addr::from(octets)).to_string()
+                    }
+                    _ => to_hex(address),
+                };
+                write!(
+                    f,
+                    "CLIENT-SUBNET: {address}/{source_prefix_length}/{scope_prefix_length}"
+                )
+            }
+            EdnsOption::TcpKeepalive(Some(timeout)) => write!(f, "KEEPALIVE: {timeout}"),
+            EdnsOption::TcpKeepalive(None) => write!(f, "KEEPALIVE"),
+            EdnsOption::Padding(len) => write!(f, "PADDING: {len}"),
+            EdnsOption::ExtendedError {
+                info_code,
+                extra_text,
+            } => write!(f, "EDE: {info_code} ({extra_text})"),
+            EdnsOption::Unknown { code, data } => write!(f, "OPT{code}: {}", to_hex(data)),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Constructors for the specially-formed names queried by service discovery and DANE-style
+//! lookups.
+
+use std::net::IpAddr;
+
+use sha2::{Digest, Sha256};
+
+use super::to_hex;
+
+/// The port `tlsa_name` uses when none is given (HTTPS)
+pub const DEFAULT_TLSA_PORT: u16 = 443;
+
+/// The name to query for SRV records of `service` over `protocol` at `domain`, as defined by
+/// [RFC 2782](https://datatracker.ietf.org/doc/html/rfc2782).  The service and protocol may be
+/// given with or without their leading underscore, e.g. `srv_name("xmpp-server", "tcp",
+/// "example.com")` is `_xmpp-server._tcp.example.com`.
+pub fn srv_name(service: &str, protocol: &str, domain: &str) -> String {
+    format!(
+        "_{}._{}.{}",
+        service.trim_start_matches('_'),
+        protocol.trim_start_matches('_'),
+        domain.trim_end_matches('.')
+    )
+}
+
+/// The name to query for TLSA records of the service on `port` over `protocol` at `host`, as
+/// defined by [RFC 6698 section 3](https://datatracker.ietf.org/doc/html/rfc6698#section-3).
+/// The port defaults to [`DEFAULT_TLSA_PORT`].
+pub fn tlsa_name(port: Option<u16>, protocol: &str, host: &str) -> String {
+    format!(
+        "_{}._{}.{}",
+        port.unwrap_or(DEFAULT_TLSA_PORT),
+        protocol.trim_start_matches('_'),
+        host.trim_end_matches('.')
+    )
+}
+
+/// The name to query for PTR records of `address`, under in-addr.arpa or ip6.arpa
+pub fn reverse_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, c, d] = address.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(address) => {
+            let mut name = String::new();
+            for byte in address.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name + "ip6.arpa"
+        }
+    }
+}
+
+/// The name to query for the OPENPGPKEY record of the mailbox `email`, as defined by [RFC 7929
+/// section 3](https://datatracker.ietf.org/doc/html/rfc7929#section-3): the first 28 bytes of
+/// the SHA-256 hash of the local part, in hex, under `_openpgpkey` at the mail domain.  Returns
+/// `None` if `email` has no `@`.
+pub fn openpgpkey_name(email: &str) -> Option<String> {
+    let (local, domain) = email.rsplit_once('@')?;
+    let hash = Sha256::digest(local.as_bytes());
+    Some(format!(
+        "{}._openpgpkey.{}",
+        to_hex(&hash[..28]),
+        domain.trim_end_matches('.')
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_srv_name() {
+        assert_eq!(
+            srv_name("xmpp-server", "tcp", "example.com"),
+            "_xmpp-server._tcp.example.com"
+        );
+        assert_eq!(
+            srv_name("_sip", "_udp", "example.com."),
+            "_sip._udp.example.com"
+        );
+    }
+
+    #[test]
+    fn test_tlsa_name() {
+        assert_eq!(
+            tlsa_name(Some(25), "tcp", "mail.example.com"),
+            "_25._tcp.mail.example.com"
+        );
+        assert_eq!(
+            tlsa_name(None, "tcp", "www.example.com."),
+            "_443._tcp.www.example.com"
+        );
+    }
+
+    #[test]
+    fn test_reverse_name() {
+        assert_eq!(
+            reverse_name(Ipv4Addr::new(8, 8, 4, 4).into()),
+            "4.4.8.8.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_name("2001:db8::567:89ab".parse().unwrap()),
+            "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+        assert_eq!(
+            reverse_name("::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn test_openpgpkey_name() {
+        // the example from RFC 7929 section 3
+        assert_eq!(
+            openpgpkey_name("hugh@example.com").unwrap(),
+            "c93f1e400f26708f98cb19d936620da35eec8f72e57f9eec01c1afd6._openpgpkey.example.com"
+        );
+        assert_eq!(openpgpkey_name("example.com"), None);
+    }
+}
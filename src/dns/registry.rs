@@ -0,0 +1,35 @@
+use std::{
+    collections::BTreeMap,
+    sync::{PoisonError, RwLock},
+};
+
+use super::QueryType;
+
+/// Parses the rdata of a record type registered with [`QueryType::register_parser`] into its
+/// presentation format.  `message` is the entire message the record came from, for
+/// decompressing any names in the rdata.
+pub type RdataParser = fn(rdata: &[u8], message: &[u8]) -> color_eyre::Result<String>;
+
+static PARSERS: RwLock<BTreeMap<u16, RdataParser>> = RwLock::new(BTreeMap::new());
+
+impl QueryType {
+    /// Teach the parser about a record type this crate doesn't know.  Records of type `ty` are
+    /// parsed with `parser` into `QueryResponse::Custom` instead of being kept as `Unknown`.
+    /// Types the crate already knows are unaffected.
+    pub fn register_parser(ty: u16, parser: RdataParser) {
+        PARSERS
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(ty, parser);
+    }
+}
+
+/// The parser registered for `ty`, if any.  Registering only ever inserts into the map, so it's
+/// still usable if a thread panicked while holding the lock.
+pub(crate) fn registered_parser(ty: u16) -> Option<RdataParser> {
+    PARSERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&ty)
+        .copied()
+}
@@ -1,11 +1,18 @@
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
 
 use clap::ValueEnum;
 use thiserror::Error;
 
+use super::EdnsOption;
+use crate::DnsError;
+
 /// A query type, as defined by [RFC 1035 section
 /// 3.2.2](https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.2)
-#[derive(Default, Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[clap(rename_all = "UPPER")]
 #[repr(u16)]
 pub enum QueryType {
@@ -60,6 +67,76 @@ pub enum QueryType {
 
     /// IPv6 address
     Aaaa = 28,
+
+    /// naming authority pointer
+    Naptr = 35,
+
+    /// EDNS pseudo-record
+    #[value(skip)]
+    Opt = 41,
+
+    /// DNSSEC signature
+    Rrsig = 46,
+
+    /// DNSSEC public key
+    Dnskey = 48,
+
+    /// DANE certificate association
+    Tlsa = 52,
+
+    /// child copy of a DS record
+    Cds = 59,
+
+    /// child copy of a DNSKEY record
+    Cdnskey = 60,
+
+    /// OpenPGP public key
+    Openpgpkey = 61,
+
+    /// zone transfer (query only)
+    Axfr = 252,
+
+    /// all records at a name (query only)
+    Any = 255,
+
+    /// a record type this crate doesn't know about
+    #[value(skip)]
+    Unknown(u16),
+}
+
+impl From<QueryType> for u16 {
+    fn from(value: QueryType) -> Self {
+        match value {
+            QueryType::A => 1,
+            QueryType::Ns => 2,
+            QueryType::Md => 3,
+            QueryType::Mf => 4,
+            QueryType::Cname => 5,
+            QueryType::Soa => 6,
+            QueryType::Mb => 7,
+            QueryType::Mg => 8,
+            QueryType::Mr => 9,
+            QueryType::Null => 10,
+            QueryType::Wks => 11,
+            QueryType::Ptr => 12,
+            QueryType::Hinfo => 13,
+            QueryType::Minfo => 14,
+            QueryType::Mx => 15,
+            QueryType::Txt => 16,
+            QueryType::Aaaa => 28,
+            QueryType::Naptr => 35,
+            QueryType::Opt => 41,
+            QueryType::Rrsig => 46,
+            QueryType::Dnskey => 48,
+            QueryType::Tlsa => 52,
+            QueryType::Cds => 59,
+            QueryType::Cdnskey => 60,
+            QueryType::Openpgpkey => 61,
+            QueryType::Axfr => 252,
+            QueryType::Any => 255,
+            QueryType::Unknown(x) => x,
+        }
+    }
 }
 
 impl From<&QueryResponse> for QueryType {
@@ -70,22 +147,38 @@ impl From<&QueryResponse> for QueryType {
             QueryResponse::Md => Self::Md,
             QueryResponse::Mf => Self::Mf,
             QueryResponse::Cname(_) => Self::Cname,
-            QueryResponse::Soa => Self::Soa,
-            QueryResponse::Mb => Self::Mb,
-            QueryResponse::Mg => Self::Mg,
-            QueryResponse::Mr => Self::Mr,
-            QueryResponse::Null => Self::Null,
-            QueryResponse::Wks => Self::Wks,
-            QueryResponse::Ptr => Self::Ptr,
-            QueryResponse::Hinfo => Self::Hinfo,
-            QueryResponse::Minfo => Self::Minfo,
-            QueryResponse::Mx => Self::Mx,
+            QueryResponse::Soa(_) => Self::Soa,
+            QueryResponse::Mb(_) => Self::Mb,
+            QueryResponse::Mg(_) => Self::Mg,
+            QueryResponse::Mr(_) => Self::Mr,
+            QueryResponse::Null(_) => Self::Null,
+            QueryResponse::Wks { .. } => Self::Wks,
+            QueryResponse::Ptr(_) => Self::Ptr,
+            QueryResponse::Hinfo { .. } => Self::Hinfo,
+            QueryResponse::Minfo { .. } => Self::Minfo,
+            QueryResponse::Mx { .. } => Self::Mx,
             QueryResponse::Txt(_) => Self::Txt,
             QueryResponse::Aaaa(_) => Self::Aaaa,
+            QueryResponse::Naptr { .. } => Self::Naptr,
+            QueryResponse::Opt { .. } => Self::Opt,
+            QueryResponse::Rrsig(_) => Self::Rrsig,
+            QueryResponse::Dnskey(_) => Self::Dnskey,
+            QueryResponse::Tlsa { .. } => Self::Tlsa,
+            QueryResponse::Cds(_) => Self::Cds,
+            QueryResponse::Cdnskey(_) => Self::Cdnskey,
+            QueryResponse::Openpgpkey(_) => Self::Openpgpkey,
+            QueryResponse::Custom { ty, .. } => Self::Unknown(*ty),
+            QueryResponse::Unknown { ty, .. } => Self::Unknown(*ty),
         }
     }
 }
 
+impl From<QueryResponse> for QueryType {
+    fn from(value: QueryResponse) -> Self {
+        Self::from(&value)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum TryFromQueryTypeError {
     #[error("Received {0}, which is an unknown query type")]
@@ -114,6 +207,16 @@ impl TryFrom<u16> for QueryType {
             15 => Self::Mx,
             16 => Self::Txt,
             28 => Self::Aaaa,
+            35 => Self::Naptr,
+            41 => Self::Opt,
+            46 => Self::Rrsig,
+            48 => Self::Dnskey,
+            52 => Self::Tlsa,
+            59 => Self::Cds,
+            60 => Self::Cdnskey,
+            61 => Self::Openpgpkey,
+            252 => Self::Axfr,
+            255 => Self::Any,
             _ => return Err(TryFromQueryTypeError::Unknown(value)),
         };
         Ok(x)
@@ -121,6 +224,7 @@ impl TryFrom<u16> for QueryType {
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum QueryResponse {
     /// host address record
     A(std::net::Ipv4Addr),
@@ -138,43 +242,107 @@ pub enum QueryResponse {
     Cname(String),
 
     /// start of a zone of authority
-    Soa,
+    Soa(Soa),
 
-    /// mailbox domain name (EXPERIMENTAL)
-    Mb,
+    /// mailbox domain name (EXPERIMENTAL): the host with the mailbox
+    Mb(String),
 
-    /// mail group member (EXPERIMENTAL)
-    Mg,
+    /// mail group member (EXPERIMENTAL): a mailbox in the mail group
+    Mg(String),
 
-    /// mail rename domain name (EXPERIMENTAL)
-    Mr,
+    /// mail rename domain name (EXPERIMENTAL): the mailbox's new name
+    Mr(String),
 
-    /// null RR (EXPERIMENTAL)
-    Null,
+    /// null RR (EXPERIMENTAL), holding up to 65535 arbitrary bytes
+    Null(Vec<u8>),
 
-    /// well-known service description
-    Wks,
+    /// well-known service description, with the ports whose bits are set in its bitmap
+    Wks {
+        address: Ipv4Addr,
+        protocol: u8,
+        ports: Vec<u16>,
+    },
 
     /// domain name pointer
-    Ptr,
+    Ptr(String),
 
     /// host information
-    Hinfo,
+    Hinfo { cpu: String, os: String },
 
-    /// mailbox or mail list information
-    Minfo,
+    /// mailbox or mail list information: the mailbox responsible for the list, and the one
+    /// errors about it should be sent to
+    Minfo { rmailbx: String, emailbx: String },
 
     /// mail exchange
-    Mx,
+    Mx { preference: u16, exchange: String },
 
     /// text strings
     Txt(String),
 
     /// IPv6 Address
     Aaaa(Ipv6Addr),
+
+    /// naming authority pointer, as defined by [RFC 3403 section
+    /// 4.1](https://datatracker.ietf.org/doc/html/rfc3403#section-4.1)
+    Naptr {
+        order: u16,
+        preference: u16,
+        flags: String,
+        services: String,
+        regexp: String,
+        replacement: String,
+    },
+
+    /// EDNS pseudo-record, carrying the sender's UDP payload size and any options
+    Opt {
+        payload_size: u16,
+        options: Vec<EdnsOption>,
+    },
+
+    /// DNSSEC signature over the records of one type at a name
+    Rrsig(Rrsig),
+
+    /// DNSSEC public key
+    Dnskey(Dnskey),
+
+    /// DANE certificate association, as defined by [RFC 6698 section
+    /// 2.1](https://datatracker.ietf.org/doc/html/rfc6698#section-2.1)
+    Tlsa {
+        usage: u8,
+        selector: u8,
+        matching_type: u8,
+        cert_data: Vec<u8>,
+    },
+
+    /// child copy of a DS record
+    Cds(Ds),
+
+    /// child copy of a DNSKEY record
+    Cdnskey(Dnskey),
+
+    /// OpenPGP transferable public key, as defined by [RFC
+    /// 7929](https://datatracker.ietf.org/doc/html/rfc7929)
+    Openpgpkey(Vec<u8>),
+
+    /// a record of a type parsed by a parser registered with `QueryType::register_parser`,
+    /// holding its presentation format.  Only produced with the `extensible` feature.
+    Custom { ty: u16, data: String },
+
+    /// a record of a type this crate doesn't know how to parse
+    Unknown { ty: u16, data: Vec<u8> },
 }
 
 impl QueryResponse {
+    /// The data of an A record for the address made of `octets`
+    pub fn a(octets: [u8; 4]) -> Self {
+        Self::A(octets.into())
+    }
+
+    /// The data of an AAAA record for the address made of `octets`
+    pub fn aaaa(octets: [u8; 16]) -> Self {
+        Self::Aaaa(octets.into())
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             QueryResponse::A(_) => "A",
@@ -182,18 +350,173 @@ impl QueryResponse {
             QueryResponse::Md => "MD",
             QueryResponse::Mf => "MF",
             QueryResponse::Cname(_) => "CNAME",
-            QueryResponse::Soa => "SOA",
-            QueryResponse::Mb => "MB",
-            QueryResponse::Mg => "MG",
-            QueryResponse::Mr => "MR",
-            QueryResponse::Null => "NULL",
-            QueryResponse::Wks => "WKS",
-            QueryResponse::Ptr => "PTR",
-            QueryResponse::Hinfo => "HINFO",
-            QueryResponse::Minfo => "MINFO",
-            QueryResponse::Mx => "MX",
+            QueryResponse::Soa(_) => "SOA",
+            QueryResponse::Mb(_) => "MB",
+            QueryResponse::Mg(_) => "MG",
+            QueryResponse::Mr(_) => "MR",
+            QueryResponse::Null(_) => "NULL",
+            QueryResponse::Wks { .. } => "WKS",
+            QueryResponse::Ptr(_) => "PTR",
+            QueryResponse::Hinfo { .. } => "HINFO",
+            QueryResponse::Minfo { .. } => "MINFO",
+            QueryResponse::Mx { .. } => "MX",
             QueryResponse::Txt(_) => "TXT",
             QueryResponse::Aaaa(_) => "AAAA",
+            QueryResponse::Naptr { .. } => "NAPTR",
+            QueryResponse::Opt { .. } => "OPT",
+            QueryResponse::Rrsig(_) => "RRSIG",
+            QueryResponse::Dnskey(_) => "DNSKEY",
+            QueryResponse::Tlsa { .. } => "TLSA",
+            QueryResponse::Cds(_) => "CDS",
+            QueryResponse::Cdnskey(_) => "CDNSKEY",
+            QueryResponse::Openpgpkey(_) => "OPENPGPKEY",
+            QueryResponse::Custom { .. } => "CUSTOM",
+            QueryResponse::Unknown { .. } => "UNKNOWN",
+        }
+    }
+
+    /// Check the type-specific invariants of this record's data.  Rdata of a known type that is
+    /// too malformed to interpret at all is kept as `Unknown`, and is rejected here.
+    pub fn validate(&self) -> Result<(), DnsError> {
+        let invalid = |reason: String| {
+            Err(DnsError::InvalidRecord {
+                ty: QueryType::from(self),
+                reason,
+            })
+        };
+        match self {
+            QueryResponse::Ns(name) | QueryResponse::Cname(name) | QueryResponse::Ptr(name)
+                if name.is_empty() =>
+            {
+                invalid("target name is empty".into())
+            }
+            QueryResponse::Soa(soa) if soa.mname.is_empty() || soa.rname.is_empty() => {
+                invalid("primary server and mailbox names must be non-empty".into())
+            }
+            // an empty exchange is only allowed as a null MX (RFC 7505)
+            QueryResponse::Mx {
+                preference,
+                exchange,
+            } if exchange.is_empty() && *preference != 0 => {
+                invalid("exchange is empty, but this isn't a null MX record".into())
+            }
+            QueryResponse::Cds(ds) if !ds.is_delete() => match (ds.digest_type, ds.digest.len()) {
+                (1, 20) | (2, 32) | (4, 48) => Ok(()),
+                (1 | 2 | 4, len) => invalid(format!(
+                    "{len}-byte digest doesn't match digest type {}",
+                    ds.digest_type
+                )),
+                _ => Ok(()),
+            },
+            QueryResponse::Dnskey(dnskey) | QueryResponse::Cdnskey(dnskey)
+                if dnskey.protocol != 3 =>
+            {
+                invalid(format!("protocol is {}, but must be 3", dnskey.protocol))
+            }
+            QueryResponse::Unknown { ty, data } => match QueryType::try_from(*ty) {
+                Ok(QueryType::A) => {
+                    invalid(format!("expected 4 bytes of rdata, got {}", data.len()))
+                }
+                Ok(QueryType::Aaaa) => {
+                    invalid(format!("expected 16 bytes of rdata, got {}", data.len()))
+                }
+                Ok(meta @ (QueryType::Any | QueryType::Axfr)) => {
+                    invalid(format!("{meta:?} is only valid in questions"))
+                }
+                Ok(_) => invalid("malformed rdata".into()),
+                Err(_) => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The rdata of a SOA record, as defined by [RFC 1035 section
+/// 3.3.13](https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.13)
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Soa {
+    pub mname: String,
+    pub rname: String,
+    pub serial: u32,
+    pub refresh: u32,
+    pub retry: u32,
+    pub expire: u32,
+    pub minimum: u32,
+}
+
+/// The rdata of a DS or CDS record, as defined by [RFC 4034 section
+/// 5.1](https://datatracker.ietf.org/doc/html/rfc4034#section-5.1)
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ds {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: Vec<u8>,
+}
+
+impl Ds {
+    /// Whether this is the CDS "delete" form, asking the parent to remove all DS records
+    /// ([RFC 8078 section 4](https://datatracker.ietf.org/doc/html/rfc8078#section-4))
+    pub fn is_delete(&self) -> bool {
+        self.algorithm == 0
+    }
+}
+
+/// The rdata of an RRSIG record, as defined by [RFC 4034 section
+/// 3.1](https://datatracker.ietf.org/doc/html/rfc4034#section-3.1).  The expiration and
+/// inception times are in seconds since the Unix epoch.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rrsig {
+    pub type_covered: QueryType,
+    pub algorithm: u8,
+    pub labels: u8,
+    pub original_ttl: u32,
+    pub expiration: u32,
+    pub inception: u32,
+    pub key_tag: u16,
+    pub signer_name: String,
+    pub signature: Vec<u8>,
+}
+
+/// The rdata of a DNSKEY or CDNSKEY record, as defined by [RFC 4034 section
+/// 2.1](https://datatracker.ietf.org/doc/html/rfc4034#section-2.1)
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dnskey {
+    pub flags: u16,
+    pub protocol: u8,
+    pub algorithm: u8,
+    pub public_key: Vec<u8>,
+}
+
+impl Dnskey {
+    /// Whether this is the CDNSKEY "delete" form, asking the parent to remove all DS records
+    /// ([RFC 8078 section 4](https://datatracker.ietf.org/doc/html/rfc8078#section-4))
+    pub fn is_delete(&self) -> bool {
+        self.algorithm == 0
+    }
+}
+
+impl From<Ipv4Addr> for QueryResponse {
+    fn from(address: Ipv4Addr) -> Self {
+        Self::A(address)
+    }
+}
+
+impl From<Ipv6Addr> for QueryResponse {
+    fn from(address: Ipv6Addr) -> Self {
+        Self::Aaaa(address)
+    }
+}
+
+impl From<IpAddr> for QueryResponse {
+    fn from(address: IpAddr) -> Self {
+        match address {
+            IpAddr::V4(address) => address.into(),
+            IpAddr::V6(address) => address.into(),
         }
     }
 }
@@ -204,9 +527,144 @@ impl Default for QueryResponse {
     }
 }
 
+/// The kind of message, as carried in the OPCODE field of the header ([RFC 1035 section
+/// 4.1.1](https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.1))
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Opcode {
+    /// a standard query
+    #[default]
+    Query,
+
+    /// an inverse query (obsolete)
+    IQuery,
+
+    /// a server status request
+    Status,
+
+    /// a zone change notification ([RFC 1996](https://datatracker.ietf.org/doc/html/rfc1996))
+    Notify,
+
+    /// a dynamic update ([RFC 2136](https://datatracker.ietf.org/doc/html/rfc2136))
+    Update,
+
+    /// an opcode this crate doesn't know about
+    Other(u8),
+}
+
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Query,
+            1 => Self::IQuery,
+            2 => Self::Status,
+            4 => Self::Notify,
+            5 => Self::Update,
+            x => Self::Other(x),
+        }
+    }
+}
+
+impl From<Opcode> for u8 {
+    fn from(value: Opcode) -> Self {
+        match value {
+            Opcode::Query => 0,
+            Opcode::IQuery => 1,
+            Opcode::Status => 2,
+            Opcode::Notify => 4,
+            Opcode::Update => 5,
+            Opcode::Other(x) => x,
+        }
+    }
+}
+
+impl Display for Opcode {
+    /// The opcode's mnemonic, as dig prints it
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Opcode::Query => write!(f, "QUERY"),
+            Opcode::IQuery => write!(f, "IQUERY"),
+            Opcode::Status => write!(f, "STATUS"),
+            Opcode::Notify => write!(f, "NOTIFY"),
+            Opcode::Update => write!(f, "UPDATE"),
+            Opcode::Other(x) => write!(f, "RESERVED{x}"),
+        }
+    }
+}
+
+/// The outcome of a query, as carried in the RCODE field of the header ([RFC 1035 section
+/// 4.1.1](https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.1))
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ResponseCode {
+    /// no error
+    #[default]
+    NoError,
+
+    /// the server couldn't interpret the query
+    FormErr,
+
+    /// the server failed to process the query
+    ServFail,
+
+    /// the queried name doesn't exist
+    NxDomain,
+
+    /// the server doesn't support this kind of query
+    NotImp,
+
+    /// the server refused to answer
+    Refused,
+
+    /// the server doesn't implement the EDNS version of the query ([RFC 6891 section
+    /// 6.1.3](https://datatracker.ietf.org/doc/html/rfc6891#section-6.1.3))
+    BadVers,
+
+    /// a response code this crate doesn't know about, of up to 12 bits with EDNS
+    Other(u16),
+}
+
+impl Display for ResponseCode {
+    /// The response code's mnemonic, as dig prints it
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponseCode::NoError => write!(f, "NOERROR"),
+            ResponseCode::FormErr => write!(f, "FORMERR"),
+            ResponseCode::ServFail => write!(f, "SERVFAIL"),
+            ResponseCode::NxDomain => write!(f, "NXDOMAIN"),
+            ResponseCode::NotImp => write!(f, "NOTIMP"),
+            ResponseCode::Refused => write!(f, "REFUSED"),
+            ResponseCode::BadVers => write!(f, "BADVERS"),
+            ResponseCode::Other(x) => write!(f, "RESERVED{x}"),
+        }
+    }
+}
+
+impl From<u16> for ResponseCode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Self::NoError,
+            1 => Self::FormErr,
+            2 => Self::ServFail,
+            3 => Self::NxDomain,
+            4 => Self::NotImp,
+            5 => Self::Refused,
+            16 => Self::BadVers,
+            x => Self::Other(x),
+        }
+    }
+}
+
+impl From<u8> for ResponseCode {
+    fn from(value: u8) -> Self {
+        Self::from(u16::from(value))
+    }
+}
+
 /// A class type, as defined by [RFC 1035 section
 /// 3.2.4](https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.4)
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u16)]
 #[allow(unused)]
 pub enum ClassType {
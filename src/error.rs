@@ -0,0 +1,84 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{QueryType, Question};
+
+/// Errors produced while sending queries and interpreting their responses.
+#[derive(Error, Debug)]
+pub enum DnsError {
+    #[error("Response question {received:?} does not match the query {expected:?}")]
+    QuestionMismatch {
+        expected: Question,
+        received: Option<Question>,
+    },
+
+    #[error("Referred to the same nameservers for zone \"{zone}\" more than once")]
+    Loop { zone: String },
+
+    #[error("No {ty:?} records found for {name}")]
+    NoData { name: String, ty: QueryType },
+
+    #[error("{name} does not exist")]
+    NxDomain { name: String },
+
+    #[error("Invalid {ty:?} record: {reason}")]
+    InvalidRecord { ty: QueryType, reason: String },
+
+    #[error("Nameserver lookups nested too deeply while resolving {name}")]
+    TooDeep { name: String },
+
+    #[error("Resolution was cancelled")]
+    Cancelled,
+
+    #[error("Response's DNS cookie is malformed or doesn't echo the client cookie sent")]
+    BadCookie,
+}
+
+/// Why a message couldn't be parsed, as returned by `parse_bytes`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    #[error(
+        "Record claims {claimed} bytes of rdata, but only {available} are left in the message"
+    )]
+    RdataTooLong { claimed: u16, available: usize },
+
+    /// Any other problem, with the chain of causes flattened into one line
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<color_eyre::Report> for ParseError {
+    fn from(report: color_eyre::Report) -> Self {
+        match report.downcast_ref::<ParseError>() {
+            Some(e) => e.clone(),
+            None => Self::Other(format!("{report:#}")),
+        }
+    }
+}
+
+/// Problems noticed while resolving a query that didn't prevent it from being answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveWarning {
+    /// The response for `name` had a CNAME record sharing its owner name with records of other
+    /// types, which RFC 1034 forbids
+    CnameCoexistence { name: String },
+    /// The response for `name` set reserved header bits, which should be zero
+    ReservedBits { name: String, bits: u8 },
+}
+
+impl Display for ResolveWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveWarning::CnameCoexistence { name } => {
+                write!(f, "Response for {name} has a CNAME alongside other records")
+            }
+            ResolveWarning::ReservedBits { name, bits } => {
+                write!(
+                    f,
+                    "Response for {name} set reserved header bits {bits:#05b}"
+                )
+            }
+        }
+    }
+}
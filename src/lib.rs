@@ -1,8 +1,21 @@
+mod cache;
 mod dns;
-use color_eyre::eyre::Context;
+mod error;
+mod resolver;
+#[cfg(feature = "dot")]
+mod tls;
+mod transport;
+pub use cache::*;
 pub use dns::*;
-use rand::{random, seq::SliceRandom, thread_rng};
-use std::net::{Ipv4Addr, Ipv6Addr, ToSocketAddrs, UdpSocket};
+pub use error::*;
+pub use resolver::*;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+#[cfg(feature = "dot")]
+pub use tls::*;
+pub use transport::*;
 
 pub static ROOT_SERVERS: [(Ipv4Addr, Ipv6Addr); 13] = [
     (
@@ -61,45 +74,144 @@ pub static ROOT_SERVERS: [(Ipv4Addr, Ipv6Addr); 13] = [
 
 /// resolve a dns query
 pub fn resolve(domain_name: &str, record_type: dns::QueryType) -> color_eyre::Result<Record> {
-    let mut rng = thread_rng();
-    let mut nameserver = ROOT_SERVERS.choose(&mut rng).unwrap().0;
-    let mut query_result: Option<dns::Record> = None;
-    loop {
-        println!("Querying {nameserver} for {}", domain_name);
-        let response = query((nameserver, 53), domain_name, record_type)?;
-        if let Some(result) = response.answers().find_map(|record| {
-            if <&dns::QueryResponse as Into<dns::QueryType>>::into(&record.ty) == record_type {
-                return Some(record.clone());
-            }
-            None
-        }) {
-            query_result = Some(result);
-            break;
-        } else if let Some(ns_ip) = response.additionals().find_map(|record| match record.ty {
-            dns::QueryResponse::A(ip_addr) => Some(ip_addr),
-            _ => None,
-        }) {
-            nameserver = ns_ip;
-        } else if let Some(ns_domain) = response.authorities().find_map(|record| match &record.ty {
-            dns::QueryResponse::Ns(ref name) => Some(name.as_str()),
-            _ => None,
-        }) {
-            let record = resolve(ns_domain, QueryType::A)?;
-            nameserver = match record.ty {
-                dns::QueryResponse::A(x) => x,
-                _ => {
-                    let ty: QueryType = (&record.ty).into();
-                    color_eyre::eyre::bail!("Expected {:?} record, got {:?}", QueryType::A, ty);
-                }
-            };
-        } else {
-            break;
-        };
-    }
-    let Some(record) = query_result else {
-            color_eyre::eyre::bail!("Unable to resolve query!")
-        };
-    Ok(record)
+    Resolver::new().resolve(domain_name, record_type)
+}
+
+/// resolve a dns query as each of `record_types` in turn, returning the first record found
+pub fn resolve_preferred(
+    domain_name: &str,
+    record_types: &[dns::QueryType],
+) -> color_eyre::Result<Record> {
+    Resolver::new().resolve_preferred(domain_name, record_types)
+}
+
+/// resolve a dns query, returning every answer of the requested type
+pub fn resolve_all(
+    domain_name: &str,
+    record_type: dns::QueryType,
+) -> color_eyre::Result<Vec<Record>> {
+    Resolver::new().resolve_all(domain_name, record_type)
+}
+
+/// resolve a dns query, sorting and/or deduplicating the answers as `options` asks
+pub fn resolve_all_with_options(
+    domain_name: &str,
+    record_type: dns::QueryType,
+    options: ResolveOptions,
+) -> color_eyre::Result<Vec<Record>> {
+    Resolver::new().resolve_all_with_options(domain_name, record_type, options)
+}
+
+/// resolve the IPv4 addresses of a domain name
+pub fn resolve_a(domain_name: &str) -> color_eyre::Result<Vec<Ipv4Addr>> {
+    Resolver::new().resolve_a(domain_name)
+}
+
+/// resolve the IPv6 addresses of a domain name
+pub fn resolve_aaaa(domain_name: &str) -> color_eyre::Result<Vec<Ipv6Addr>> {
+    Resolver::new().resolve_aaaa(domain_name)
+}
+
+/// resolve a dns query, giving up once `cancel` is set
+pub fn resolve_cancellable(
+    domain_name: &str,
+    record_type: dns::QueryType,
+    cancel: &std::sync::atomic::AtomicBool,
+) -> color_eyre::Result<Record> {
+    Resolver::new().resolve_cancellable(domain_name, record_type, cancel)
+}
+
+/// resolve a dns query and check its answers against `expected`, ignoring order
+pub fn resolve_expect(
+    domain_name: &str,
+    record_type: dns::QueryType,
+    expected: &[&str],
+) -> color_eyre::Result<AnswerCheck> {
+    Resolver::new().resolve_expect(domain_name, record_type, expected)
+}
+
+/// resolve many dns queries in parallel, returning the results in the same order as `names`
+pub fn resolve_many(
+    names: &[&str],
+    record_type: dns::QueryType,
+) -> Vec<(String, color_eyre::Result<Record>)> {
+    Resolver::new().resolve_many(names, record_type)
+}
+
+/// resolve a dns query, starting from `servers` (on port 53) instead of the root servers.
+/// Useful for private roots or split-horizon setups where the public roots are of no use.
+pub fn resolve_with_servers(
+    domain_name: &str,
+    record_type: dns::QueryType,
+    servers: &[IpAddr],
+) -> color_eyre::Result<Record> {
+    let servers: Vec<_> = servers
+        .iter()
+        .map(|server| SocketAddr::from((*server, 53)))
+        .collect();
+    Resolver::new()
+        .with_servers(&servers)
+        .resolve(domain_name, record_type)
+}
+
+/// Send a query to each of `servers` in turn, returning the first response received
+pub fn query_any_of(
+    servers: &[SocketAddr],
+    domain_name: &str,
+    record_type: dns::QueryType,
+) -> color_eyre::Result<Response> {
+    Resolver::new().query_any_of(servers, domain_name, record_type)
+}
+
+/// Resolve the IPv6 and IPv4 addresses of `host`, pairing each with `port`
+pub fn resolve_socket_addrs(host: &str, port: u16) -> color_eyre::Result<Vec<SocketAddr>> {
+    Resolver::new().resolve_socket_addrs(host, port)
+}
+
+/// Send a query over DNS-over-TLS to `server` (a host name or IP address) on port 853, checking
+/// its certificate is valid for `server`
+#[cfg(feature = "dot")]
+pub fn query_tls(
+    server: &str,
+    domain_name: &str,
+    record_type: dns::QueryType,
+) -> color_eyre::Result<dns::Response> {
+    Resolver::new()
+        .with_transport(TlsTransport::new(server)?)
+        .query_server((server, DOT_PORT), domain_name, record_type)
+}
+
+/// Send a query to the DNS-over-HTTPS endpoint at `url` in a POST request
+#[cfg(feature = "doh")]
+pub fn query_https(
+    url: &str,
+    domain_name: &str,
+    record_type: dns::QueryType,
+) -> color_eyre::Result<dns::Response> {
+    Resolver::new().query_https(url, domain_name, record_type)
+}
+
+/// Send a query to the DNS-over-HTTPS endpoint at `url` in a GET request
+#[cfg(feature = "doh")]
+pub fn query_https_get(
+    url: &str,
+    domain_name: &str,
+    record_type: dns::QueryType,
+) -> color_eyre::Result<dns::Response> {
+    Resolver::new().query_https_get(url, domain_name, record_type)
+}
+
+/// Transfer a zone from the server at `address` over TCP
+pub fn axfr<A>(address: A, zone: &str) -> color_eyre::Result<Vec<Record>>
+where
+    A: ToSocketAddrs,
+{
+    Resolver::new().axfr(address, zone)
+}
+
+/// Resolve `host` and connect to the first of its addresses to accept a TCP connection
+pub fn connect_tcp(host: &str, port: u16, timeout: Duration) -> color_eyre::Result<TcpStream> {
+    Resolver::new().connect_tcp(host, port, timeout)
 }
 
 pub fn query<A>(
@@ -110,16 +222,5 @@ pub fn query<A>(
 where
     A: ToSocketAddrs,
 {
-    let query = build_query(domain_name, record_type, random());
-    let connection = UdpSocket::bind("0.0.0.0:0").context("Unable to bind to socket")?;
-
-    connection
-        .send_to(&query, address)
-        .context("Failed to send query to server")?;
-
-    let mut buf = [0u8; 1024];
-    let (size, _) = connection
-        .recv_from(&mut buf)
-        .context("No response received")?;
-    Response::parse(&buf[..size]).context("Failed to parse response")
+    Resolver::new().query_server(address, domain_name, record_type)
 }
@@ -1,8 +1,18 @@
-use std::net::Ipv4Addr;
+use std::{
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use clap::{command, Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use color_eyre::{eyre::Context, owo_colors::OwoColorize};
-use dns_query::{query, resolve, QueryType, ROOT_SERVERS};
+#[cfg(feature = "serde")]
+use dns_query::Section;
+use dns_query::{
+    resolve, resolve_expect, reverse_name, ClassType, Header, QueryBuilder, QueryType, Resolver,
+    TcpTransport, Transport, UdpTransport, DEFAULT_RETRIES, DEFAULT_TIMEOUT, ROOT_SERVERS,
+};
 use rand::{seq::SliceRandom, thread_rng};
 
 #[derive(Parser)]
@@ -20,6 +30,111 @@ enum Commands {
 
     /// Recursively resolve a query
     Resolve(ResolveArgs),
+
+    /// Look up the hostname of an IP address
+    Reverse(ReverseArgs),
+
+    /// Resolve a query and check the answers against the expected ones, exiting with a non-zero
+    /// status if they differ
+    Check(CheckArgs),
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum OutputFormat {
+    /// Colored, column-aligned output
+    #[default]
+    Human,
+
+    /// One JSON array of records, for consumption by scripts
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+/// A record as emitted by `--output json`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonRecord<'a> {
+    section: Section,
+    name: &'a str,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    data: String,
+    ttl: u32,
+}
+
+#[cfg(feature = "serde")]
+fn print_json<'a>(
+    records: impl IntoIterator<Item = (Section, &'a dns_query::Record)>,
+) -> color_eyre::Result<()> {
+    let records: Vec<_> = records
+        .into_iter()
+        .map(|(section, record)| JsonRecord {
+            section,
+            name: &record.name,
+            ty: record.ty.name(),
+            data: record.data(),
+            ttl: record.ttl,
+        })
+        .collect();
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&records).context("Failed to serialize records")?
+    );
+    Ok(())
+}
+
+/// Print `records` under `title`, with the types and data aligned in columns
+fn print_section<'a>(title: &str, records: impl Iterator<Item = &'a dns_query::Record>) {
+    let records: Vec<_> = records
+        .map(|record| (record, record.ty.name(), record.data()))
+        .collect();
+    if records.is_empty() {
+        return;
+    }
+    println!("{title}:");
+    let type_width = records.iter().map(|x| x.1.len()).max().unwrap_or_default();
+    let data_width = records.iter().map(|x| x.2.len()).max().unwrap_or_default();
+    for (record, response_type, data) in records {
+        println!(
+            "{}: {:>type_width$}|{:<data_width$} ({})",
+            record.name.purple(),
+            response_type.yellow(),
+            data.red(),
+            record.ttl.white().bold(),
+            type_width = type_width,
+            data_width = data_width,
+        );
+    }
+}
+
+/// Print just the data of each of `records`, one per line and without colors
+fn print_short<'a>(records: impl Iterator<Item = &'a dns_query::Record>) {
+    for record in records {
+        println!("{}", record.data());
+    }
+}
+
+/// The fields of the flags word in `header`, e.g. `qr opcode=Query rd ra z=0 rcode=0`
+fn flags_summary(header: &Header) -> String {
+    let mut fields = vec![];
+    if header.response() {
+        fields.push("qr".to_string());
+    }
+    fields.push(format!("opcode={:?}", header.opcode()));
+    let bits = [
+        ("aa", header.authoritative()),
+        ("tc", header.truncated()),
+        ("rd", header.recursion_desired()),
+        ("ra", header.recursion_available()),
+    ];
+    fields.extend(
+        bits.into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(name, _)| name.to_string()),
+    );
+    fields.push(format!("z={}", header.z()));
+    fields.push(format!("rcode={}", header.rcode()));
+    fields.join(" ")
 }
 
 #[derive(Args)]
@@ -27,104 +142,192 @@ struct QueryArgs {
     /// Domain name to look up records for
     domain_name: String,
 
-    /// Dns server to query
-    #[arg(short, long)]
-    dns_server_address: Option<Ipv4Addr>,
+    /// Dns server to query, by address or hostname (looked up with the system resolver)
+    #[arg(short, long, visible_alias = "dns-server")]
+    dns_server_address: Option<String>,
 
-    /// Query type to perform
-    #[arg(value_enum, short, long)]
+    /// Port the dns server listens on
+    #[arg(short, long, default_value_t = 53)]
+    port: u16,
+
+    /// Query type to perform, by name or numeric type code
+    #[arg(short, long, value_parser = parse_record_type)]
     record_type: dns_query::QueryType,
+
+    /// Class of the query, e.g. ch for `version.bind` queries
+    #[arg(value_enum, short, long, default_value_t)]
+    class: ClassType,
+
+    /// Output format
+    #[arg(value_enum, short, long, default_value_t)]
+    output: OutputFormat,
+
+    /// Ask the server to resolve the query recursively (sets the RD bit)
+    #[arg(long)]
+    recurse: bool,
+
+    /// Raw 16-bit header flags word to send, e.g. 0x0100.  Overrides --recurse.
+    #[arg(long, value_parser = parse_flags)]
+    raw_flags: Option<u16>,
+
+    /// Print the header flags of the response, broken out into their fields
+    #[arg(long)]
+    show_flags: bool,
+
+    /// How long to wait for a response to each attempt, e.g. 2s or 500ms
+    #[arg(long, value_parser = parse_timeout, default_value_t = DEFAULT_TIMEOUT.into())]
+    timeout: humantime::Duration,
+
+    /// How many times to re-send the query if no response arrives in time
+    #[arg(long, default_value_t = DEFAULT_RETRIES)]
+    retries: u32,
+
+    /// Print only the data of each answer, one per line, like `dig +short`
+    #[arg(long, conflicts_with = "output")]
+    short: bool,
+
+    /// Send the query over TCP instead of UDP
+    #[arg(long)]
+    tcp: bool,
+
+    /// Print the whole response laid out like dig's output
+    #[arg(long, conflicts_with_all = ["output", "short", "show_flags"])]
+    dig: bool,
+
+    /// Save the raw response to this file, to replay later with `Response::from_bytes_file`
+    #[arg(long)]
+    capture: Option<PathBuf>,
+}
+
+/// Sends queries over another transport, keeping the last response received so it can be saved
+struct CapturingTransport {
+    inner: Box<dyn Transport>,
+    last_response: Mutex<Option<Vec<u8>>>,
+}
+
+impl CapturingTransport {
+    fn new(inner: impl Transport + 'static) -> Self {
+        Self {
+            inner: Box::new(inner),
+            last_response: Mutex::new(None),
+        }
+    }
+}
+
+impl Transport for CapturingTransport {
+    fn send_and_recv(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+        timeout: Duration,
+        max_size: usize,
+    ) -> color_eyre::Result<Option<Vec<u8>>> {
+        let response = self
+            .inner
+            .send_and_recv(server, request, timeout, max_size)?;
+        if let Some(response) = &response {
+            *self.last_response.lock().unwrap() = Some(response.clone());
+        }
+        Ok(response)
+    }
+}
+
+/// Parse a flags word given in hexadecimal (with a `0x` prefix) or decimal
+fn parse_flags(s: &str) -> Result<u16, std::num::ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
+/// Parse a query type given by name (e.g. `MX`) or by its decimal type code (e.g. `257`).  Codes
+/// this crate has no name for are kept as `QueryType::Unknown`.
+fn parse_record_type(s: &str) -> Result<QueryType, String> {
+    if let Ok(code) = s.parse::<u16>() {
+        return Ok(QueryType::try_from(code).unwrap_or(QueryType::Unknown(code)));
+    }
+    QueryType::from_str(s, true)
+}
+
+/// Parse a non-zero duration such as `2s` or `500ms`
+fn parse_timeout(s: &str) -> Result<humantime::Duration, String> {
+    let timeout = humantime::parse_duration(s).map_err(|e| e.to_string())?;
+    if timeout.is_zero() {
+        return Err("timeout must be greater than zero".into());
+    }
+    Ok(timeout.into())
 }
 
 impl QueryArgs {
+    fn query_builder(&self) -> QueryBuilder {
+        let builder = QueryBuilder::new()
+            .recursion_desired(self.recurse)
+            .class(self.class);
+        match self.raw_flags {
+            Some(flags) => builder.flags(flags),
+            None => builder,
+        }
+    }
+
+    /// The address of the server to query, defaulting to a random root server
+    fn server_address(&self) -> color_eyre::Result<SocketAddr> {
+        let Some(server) = &self.dns_server_address else {
+            let root = ROOT_SERVERS.choose(&mut thread_rng()).unwrap().0;
+            return Ok((root, self.port).into());
+        };
+        (server.as_str(), self.port)
+            .to_socket_addrs()
+            .with_context(|| format!("Unable to look up dns server {server}"))?
+            .next()
+            .ok_or_else(|| color_eyre::eyre::eyre!("dns server {server} has no addresses"))
+    }
+
     fn exec(&self) -> color_eyre::Result<()> {
-        let dns_server_addr = self
-            .dns_server_address
-            .unwrap_or_else(|| ROOT_SERVERS.choose(&mut thread_rng()).unwrap().0);
-        let response = query((dns_server_addr, 53), &self.domain_name, self.record_type)
+        let transport = Arc::new(if self.tcp {
+            CapturingTransport::new(TcpTransport::new())
+        } else {
+            CapturingTransport::new(UdpTransport::new())
+        });
+        let server = self.server_address()?;
+        let start = Instant::now();
+        let response = Resolver::new()
+            .with_transport(transport.clone())
+            .with_query_builder(self.query_builder())
+            .with_timeout(*self.timeout)
+            .with_retries(self.retries)
+            .query_server(server, &self.domain_name, self.record_type)
             .context("Failed to retrieve response")?;
+        let query_time = start.elapsed();
+        let raw = transport.last_response.lock().unwrap().take();
+        if let Some(path) = &self.capture {
+            std::fs::write(path, raw.as_deref().unwrap_or_default())
+                .with_context(|| format!("Unable to save response to {}", path.display()))?;
+        }
 
-        fn fetch_data(record: &dns_query::Record) -> (&dns_query::Record, &'static str, String) {
-            // let fetch_data = |record: &dns::Record| {
-            let data = record.data();
-            (record, record.ty.name(), data)
+        if self.short {
+            print_short(response.answers());
+            return Ok(());
         }
-        let print_output =
-            |(record, response_type, data): (&dns_query::Record, &'static str, String),
-             type_width: usize,
-             data_width: usize| {
-                println!(
-                    "{}: {:>type_width$}|{:<data_width$} ({})",
-                    record.name.purple(),
-                    response_type.yellow(),
-                    data.red(),
-                    record.ttl.white().bold(),
-                    type_width = type_width,
-                    data_width = data_width,
-                );
-            };
-        // Answers
-        if response.answers().count() > 0 {
-            println!("Answers:");
-            let longest_data = response
-                .answers()
-                .map(fetch_data)
-                .map(|x| x.2.len())
-                .max()
-                .unwrap_or_default();
-            let longest_type = response
-                .answers()
-                .map(fetch_data)
-                .map(|x| x.1.len())
-                .max()
-                .unwrap_or_default();
-            response
-                .answers()
-                .map(fetch_data)
-                .for_each(|x| print_output(x, longest_type, longest_data));
+        if self.dig {
+            print!("{}", response.to_dig_format());
+            println!();
+            println!(";; Query time: {} msec", query_time.as_millis());
+            println!(";; SERVER: {}#{}", server.ip(), server.port());
+            println!(";; MSG SIZE  rcvd: {}", raw.map_or(0, |raw| raw.len()));
+            return Ok(());
         }
-
-        // Authorities
-        if response.authorities().count() > 0 {
-            println!("Authorities:");
-            let longest_data = response
-                .authorities()
-                .map(fetch_data)
-                .map(|x| x.2.len())
-                .max()
-                .unwrap_or_default();
-            let longest_type = response
-                .authorities()
-                .map(fetch_data)
-                .map(|x| x.1.len())
-                .max()
-                .unwrap_or_default();
-            response
-                .authorities()
-                .map(fetch_data)
-                .for_each(|x| print_output(x, longest_type, longest_data));
+        match self.output {
+            OutputFormat::Human => {}
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => return print_json(response.all_records()),
         }
 
-        // Additionals
-        if response.additionals().count() > 0 {
-            println!("Additionals:");
-            let longest_data = response
-                .additionals()
-                .map(fetch_data)
-                .map(|x| x.2.len())
-                .max()
-                .unwrap_or_default();
-            let longest_type = response
-                .additionals()
-                .map(fetch_data)
-                .map(|x| x.1.len())
-                .max()
-                .unwrap_or_default();
-            response
-                .additionals()
-                .map(fetch_data)
-                .for_each(|x| print_output(x, longest_type, longest_data));
+        if self.show_flags {
+            println!("Flags: {}", flags_summary(response.header()));
         }
+        print_section("Answers", response.answers());
+        print_section("Authorities", response.authorities());
+        print_section("Additionals", response.additionals());
 
         Ok(())
     }
@@ -135,9 +338,93 @@ struct ResolveArgs {
     /// the hostname to resolve
     domain_name: String,
 
-    /// the record type to query
-    #[arg(short)]
+    /// the record type to query, by name or numeric type code
+    #[arg(short, value_parser = parse_record_type)]
+    record_type: QueryType,
+
+    /// Output format
+    #[arg(value_enum, short, long, default_value_t)]
+    output: OutputFormat,
+
+    /// Print only the data of each record, one per line, like `dig +short`
+    #[arg(long, conflicts_with = "output")]
+    short: bool,
+
+    /// Send every query over TCP instead of UDP
+    #[arg(long)]
+    tcp: bool,
+}
+
+impl ResolveArgs {
+    fn exec(&self) -> color_eyre::Result<()> {
+        let resolver = if self.tcp {
+            Resolver::new().with_transport(TcpTransport::new())
+        } else {
+            Resolver::new()
+        };
+        let records = resolver.resolve_all(&self.domain_name, self.record_type)?;
+        if self.short {
+            print_short(records.iter());
+            return Ok(());
+        }
+        match self.output {
+            OutputFormat::Human => print_section("Answers", records.iter()),
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => {
+                return print_json(records.iter().map(|record| (Section::Answer, record)))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct ReverseArgs {
+    /// the address to look up
+    address: IpAddr,
+
+    /// Output format
+    #[arg(value_enum, short, long, default_value_t)]
+    output: OutputFormat,
+}
+
+impl ReverseArgs {
+    fn exec(&self) -> color_eyre::Result<()> {
+        let record = resolve(&reverse_name(self.address), QueryType::Ptr)?;
+        match self.output {
+            OutputFormat::Human => {}
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => return print_json([(Section::Answer, &record)]),
+        }
+        print_section("Answers", std::iter::once(&record));
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// the hostname to resolve
+    domain_name: String,
+
+    /// the record type to query, by name or numeric type code
+    #[arg(short, value_parser = parse_record_type)]
     record_type: QueryType,
+
+    /// the expected answers, in any order
+    #[arg(required = true)]
+    expected: Vec<String>,
+}
+
+impl CheckArgs {
+    fn exec(&self) -> color_eyre::Result<()> {
+        let expected: Vec<_> = self.expected.iter().map(String::as_str).collect();
+        let check = resolve_expect(&self.domain_name, self.record_type, &expected)?;
+        println!("{check}");
+        if !check.matches() {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
 }
 
 fn main() -> color_eyre::Result<()> {
@@ -145,27 +432,244 @@ fn main() -> color_eyre::Result<()> {
 
     let app = App::parse();
     match app.command {
-        Commands::Query(q) => return q.exec(),
-        Commands::Resolve(r) => {
-            let record = resolve(&r.domain_name, r.record_type)?;
-            println!(
-                "{}: {}|{} ({})",
-                record.name.purple(),
-                record.ty.name(),
-                record.data().red(),
-                record.ttl.white()
-            );
-        }
+        Commands::Query(q) => q.exec(),
+        Commands::Reverse(r) => r.exec(),
+        Commands::Check(c) => c.exec(),
+        Commands::Resolve(r) => r.exec(),
     }
-    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+
     #[test]
     fn verify_cli() {
         use clap::CommandFactory;
         App::command().debug_assert()
     }
+
+    #[test]
+    fn raw_flags() {
+        let app = App::parse_from([
+            "dns-query",
+            "query",
+            "example.com",
+            "-r",
+            "A",
+            "--raw-flags",
+            "0x0120",
+        ]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        let query = args
+            .query_builder()
+            .build(&args.domain_name, args.record_type, 0);
+        assert_eq!(&query[2..4], b"\x01\x20");
+    }
+
+    #[test]
+    fn recurse() {
+        let app = App::parse_from(["dns-query", "query", "example.com", "-r", "A", "--recurse"]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        let query = args
+            .query_builder()
+            .build(&args.domain_name, args.record_type, 0);
+        assert_eq!(&query[2..4], b"\x01\x00");
+
+        let app = App::parse_from(["dns-query", "query", "example.com", "-r", "A"]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        let query = args
+            .query_builder()
+            .build(&args.domain_name, args.record_type, 0);
+        assert_eq!(&query[2..4], b"\x00\x00");
+    }
+
+    #[test]
+    fn chaos_class() {
+        let app = App::parse_from([
+            "dns-query",
+            "query",
+            "version.bind",
+            "-r",
+            "TXT",
+            "--class",
+            "ch",
+        ]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        let query = args
+            .query_builder()
+            .build(&args.domain_name, args.record_type, 0);
+        assert_eq!(&query[query.len() - 4..], b"\x00\x10\x00\x03");
+    }
+
+    #[test]
+    fn short() {
+        let app = App::parse_from(["dns-query", "resolve", "example.com", "-r", "A", "--short"]);
+        let Commands::Resolve(args) = app.command else {
+            panic!("expected the resolve subcommand");
+        };
+        assert!(args.short);
+
+        let app = App::try_parse_from([
+            "dns-query",
+            "query",
+            "example.com",
+            "-r",
+            "A",
+            "--short",
+            "--output",
+            "human",
+        ]);
+        assert!(app.is_err());
+    }
+
+    #[test]
+    fn tcp() {
+        let app = App::parse_from(["dns-query", "query", "example.com", "-r", "A", "--tcp"]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        assert!(args.tcp);
+
+        let app = App::parse_from(["dns-query", "resolve", "example.com", "-r", "A", "--tcp"]);
+        let Commands::Resolve(args) = app.command else {
+            panic!("expected the resolve subcommand");
+        };
+        assert!(args.tcp);
+    }
+
+    #[test]
+    fn dig() {
+        let app = App::parse_from(["dns-query", "query", "example.com", "-r", "A", "--dig"]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        assert!(args.dig);
+
+        let app = App::try_parse_from([
+            "dns-query",
+            "query",
+            "example.com",
+            "-r",
+            "A",
+            "--dig",
+            "--short",
+        ]);
+        assert!(app.is_err());
+    }
+
+    #[test]
+    fn timeout_and_retries() {
+        let app = App::parse_from([
+            "dns-query",
+            "query",
+            "example.com",
+            "-r",
+            "A",
+            "--timeout",
+            "2s",
+            "--retries",
+            "5",
+        ]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        assert_eq!(*args.timeout, Duration::from_secs(2));
+        assert_eq!(args.retries, 5);
+
+        let app = App::parse_from(["dns-query", "query", "example.com", "-r", "A"]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        assert_eq!(*args.timeout, DEFAULT_TIMEOUT);
+        assert_eq!(args.retries, DEFAULT_RETRIES);
+
+        let result = App::try_parse_from([
+            "dns-query",
+            "query",
+            "example.com",
+            "-r",
+            "A",
+            "--timeout",
+            "0s",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn numeric_record_type() {
+        assert_eq!(parse_record_type("MX").unwrap(), QueryType::Mx);
+        assert_eq!(parse_record_type("aaaa").unwrap(), QueryType::Aaaa);
+        assert_eq!(parse_record_type("15").unwrap(), QueryType::Mx);
+        assert_eq!(parse_record_type("257").unwrap(), QueryType::Unknown(257));
+        assert!(parse_record_type("BOGUS").is_err());
+        assert!(parse_record_type("70000").is_err());
+
+        let app = App::parse_from(["dns-query", "query", "example.com", "-r", "257"]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        let query = args
+            .query_builder()
+            .build(&args.domain_name, args.record_type, 0);
+        assert_eq!(&query[query.len() - 4..query.len() - 2], b"\x01\x01");
+    }
+
+    #[test]
+    fn dns_server_hostname() {
+        let app = App::parse_from([
+            "dns-query",
+            "query",
+            "example.com",
+            "-r",
+            "A",
+            "--dns-server",
+            "localhost",
+            "-p",
+            "5353",
+        ]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        let address = args.server_address().unwrap();
+        assert!(address.ip().is_loopback());
+        assert_eq!(address.port(), 5353);
+
+        let app = App::parse_from(["dns-query", "query", "example.com", "-r", "A", "-d", "::1"]);
+        let Commands::Query(args) = app.command else {
+            panic!("expected the query subcommand");
+        };
+        assert_eq!(
+            args.server_address().unwrap(),
+            "[::1]:53".parse::<SocketAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn show_flags() {
+        let query =
+            QueryBuilder::new()
+                .recursion_desired(true)
+                .build("example.com", QueryType::A, 1);
+        let mut response = query.clone();
+        // set QR, RA, and an RCODE of 3 (NXDOMAIN)
+        response[2] |= 0x80;
+        response[3] |= 0x83;
+        let response = dns_query::Response::parse(&response).unwrap();
+        assert_eq!(
+            flags_summary(response.header()),
+            "qr opcode=Query rd ra z=0 rcode=3"
+        );
+
+        let query = dns_query::Response::parse(&query).unwrap();
+        assert_eq!(flags_summary(query.header()), "opcode=Query rd z=0 rcode=0");
+    }
 }
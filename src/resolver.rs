@@ -0,0 +1,2093 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use color_eyre::eyre::Context;
+use rand::{random, seq::SliceRandom, thread_rng};
+
+use crate::{
+    read_tcp_message, write_tcp_message, Cache, DnsError, QueryBuilder, QueryResponse, QueryType,
+    Question, Record, ResolveWarning, Response, ResponseCode, ResponseKind, Transport,
+    UdpTransport, ROOT_SERVERS,
+};
+
+/// Default cap on the number of additional records inspected per referral.
+pub const DEFAULT_MAX_ADDITIONALS_SCANNED: usize = 64;
+
+/// Default size of the buffer responses are received into.
+pub const DEFAULT_BUFFER_SIZE: usize = 1024;
+
+/// Default time to wait for a response to each attempt of a query.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default number of times a query is re-sent after timing out.
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// Default number of names `resolve_many` resolves at once.
+pub const DEFAULT_CONCURRENCY: usize = 16;
+
+/// Default limit on how deeply `resolve` nests lookups of nameserver names.
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// The media type of DNS messages sent over HTTPS ([RFC 8484 section
+/// 6](https://datatracker.ietf.org/doc/html/rfc8484#section-6))
+#[cfg(feature = "doh")]
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// The largest a DNS message can be, as its length has to fit TCP's 16-bit length prefix
+const MAX_MESSAGE_SIZE: usize = u16::MAX as usize;
+
+/// Delay before the first retry of a query, doubled for each subsequent retry.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Source of message ids for outgoing queries.
+pub trait IdGenerator: Send + Sync {
+    fn next_id(&self) -> u16;
+}
+
+/// Generates ids using the thread-local random number generator.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct ThreadRngIds;
+
+impl IdGenerator for ThreadRngIds {
+    fn next_id(&self) -> u16 {
+        random()
+    }
+}
+
+/// Always returns the same id.  Useful for producing deterministic queries in tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedId(pub u16);
+
+impl IdGenerator for FixedId {
+    fn next_id(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Sends queries to DNS servers, over UDP unless given another `Transport`.
+pub struct Resolver {
+    transport: Box<dyn Transport>,
+    servers: Vec<SocketAddr>,
+    id_generator: Box<dyn IdGenerator>,
+    query_builder: QueryBuilder,
+    max_additionals_scanned: usize,
+    buffer_size: usize,
+    cache: Option<Mutex<Cache>>,
+    timeout: Duration,
+    retries: u32,
+    strict_bailiwick: bool,
+    lenient_validation: bool,
+    nameserver_port: u16,
+    concurrency: usize,
+    max_depth: usize,
+    warnings: Mutex<Vec<ResolveWarning>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        let mut servers: Vec<_> = ROOT_SERVERS
+            .iter()
+            .map(|(v4, _)| SocketAddr::from((*v4, 53)))
+            .collect();
+        servers.shuffle(&mut thread_rng());
+        Self {
+            transport: Box::new(UdpTransport::new()),
+            servers,
+            id_generator: Box::new(ThreadRngIds),
+            query_builder: QueryBuilder::default(),
+            max_additionals_scanned: DEFAULT_MAX_ADDITIONALS_SCANNED,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            cache: None,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            strict_bailiwick: false,
+            lenient_validation: false,
+            nameserver_port: 53,
+            concurrency: DEFAULT_CONCURRENCY,
+            max_depth: DEFAULT_MAX_DEPTH,
+            warnings: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send queries made with `query` to `servers`, instead of to the root servers.
+    pub fn with_servers(mut self, servers: &[SocketAddr]) -> Self {
+        self.servers = servers.to_vec();
+        self
+    }
+
+    /// Use `id_generator` to pick the id of each outgoing query.
+    pub fn with_id_generator<G>(mut self, id_generator: G) -> Self
+    where
+        G: IdGenerator + 'static,
+    {
+        self.id_generator = Box::new(id_generator);
+        self
+    }
+
+    /// Send queries over `transport`, instead of over UDP.
+    pub fn with_transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.transport = Box::new(transport);
+        self
+    }
+
+    /// Send queries over UDP from `address`, instead of letting the system pick one.  This
+    /// replaces any transport set with `with_transport`.
+    pub fn with_local_address(mut self, address: IpAddr) -> Self {
+        self.transport = Box::new(UdpTransport::new().with_local_address(address));
+        self
+    }
+
+    /// Encode outgoing queries with `query_builder`.
+    pub fn with_query_builder(mut self, query_builder: QueryBuilder) -> Self {
+        self.query_builder = query_builder;
+        self
+    }
+
+    /// Inspect at most `max` additional records of a referral when looking for glue.  This
+    /// bounds the work a server can cause by stuffing a response with additionals.
+    pub fn with_max_additionals_scanned(mut self, max: usize) -> Self {
+        self.max_additionals_scanned = max;
+        self
+    }
+
+    /// Receive responses into a buffer of `size` bytes.  A response that fills the buffer may have
+    /// been cut short, so the query is sent again with a buffer large enough for any message.
+    pub fn with_buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Cache the results of `resolve`, holding at most `max_entries` names.
+    pub fn with_cache(mut self, max_entries: usize) -> Self {
+        self.cache = Some(Mutex::new(Cache::new(max_entries)));
+        self
+    }
+
+    /// Wait at most `timeout` for a response to each attempt of a query.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Re-send a query up to `retries` times if no response arrives in time.  Each retry uses a
+    /// fresh id, and waits twice as long as the previous one before being sent.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Discard authority and additional records for names outside the zone the queried server
+    /// is authoritative for, guarding `resolve` against cache-poisoning attempts.
+    pub fn with_strict_bailiwick(mut self, strict_bailiwick: bool) -> Self {
+        self.strict_bailiwick = strict_bailiwick;
+        self
+    }
+
+    /// Accept records that fail validation instead of rejecting the whole response.  With the
+    /// `tracing` feature, a warning is logged for each.
+    pub fn with_lenient_validation(mut self, lenient_validation: bool) -> Self {
+        self.lenient_validation = lenient_validation;
+        self
+    }
+
+    /// Contact nameservers found while following referrals on `port`, instead of on port 53.
+    pub fn with_nameserver_port(mut self, port: u16) -> Self {
+        self.nameserver_port = port;
+        self
+    }
+
+    /// Resolve at most `concurrency` names at once in `resolve_many`.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Give up once `resolve` has to look up the name of a nameserver while already `depth`
+    /// lookups deep, e.g. for a chain of nameservers each named within the zone of the next.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Build a query for `domain_name`, using the next id from the id generator.
+    pub fn build_query(&self, domain_name: &str, record_type: QueryType) -> Vec<u8> {
+        self.query_builder
+            .build(domain_name, record_type, self.id_generator.next_id())
+    }
+
+    /// The local ports the transport currently sends queries from.  With the default UDP
+    /// transport, these are the ports of the sockets kept for reuse between queries.
+    pub fn local_ports(&self) -> Vec<u16> {
+        self.transport.local_ports()
+    }
+
+    /// Take the warnings noticed by `resolve` since they were last taken.
+    pub fn take_warnings(&self) -> Vec<ResolveWarning> {
+        std::mem::take(&mut self.warnings.lock().unwrap())
+    }
+
+    /// Send a query to the configured upstream servers.
+    pub fn query(&self, domain_name: &str, record_type: QueryType) -> color_eyre::Result<Response> {
+        let Some(server) = self.servers.first() else {
+            color_eyre::eyre::bail!("No upstream servers configured");
+        };
+        self.query_server(server, domain_name, record_type)
+    }
+
+    /// Send a query to each of `servers` in turn, returning the first response received, like a
+    /// stub resolver working down its list of nameservers.  Each server gets the configured
+    /// timeout and retries before the next is tried.
+    pub fn query_any_of(
+        &self,
+        servers: &[SocketAddr],
+        domain_name: &str,
+        record_type: QueryType,
+    ) -> color_eyre::Result<Response> {
+        let mut errors = vec![];
+        for server in servers {
+            match self.query_server(server, domain_name, record_type) {
+                Ok(response) => return Ok(response),
+                Err(e) => errors.push(format!("{server}: {e:#}")),
+            }
+        }
+        if errors.is_empty() {
+            color_eyre::eyre::bail!("No servers to query");
+        }
+        color_eyre::eyre::bail!("No server answered ({})", errors.join("; "))
+    }
+
+    /// Send a query to the server at `address`.
+    pub fn query_server<A>(
+        &self,
+        address: A,
+        domain_name: &str,
+        record_type: QueryType,
+    ) -> color_eyre::Result<Response>
+    where
+        A: ToSocketAddrs,
+    {
+        let Some(server) = address
+            .to_socket_addrs()
+            .context("Unable to resolve server address")?
+            .next()
+        else {
+            color_eyre::eyre::bail!("Server address resolved to nothing");
+        };
+        self.query_server_with_cancel(server, domain_name, record_type, None)
+    }
+
+    /// Send a query to `server` like `query_server`, giving up with `DnsError::Cancelled` once
+    /// `cancel` is set.  The flag is checked before each attempt, including after the backoff
+    /// between retries.
+    fn query_server_with_cancel(
+        &self,
+        server: SocketAddr,
+        domain_name: &str,
+        record_type: QueryType,
+        cancel: Option<&AtomicBool>,
+    ) -> color_eyre::Result<Response> {
+        // make room for the largest response we've told the server we accept
+        let advertised = self.query_builder.payload_size().unwrap_or_default() as usize;
+        let mut max_size = self.buffer_size.max(advertised);
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 0..=self.retries {
+            if attempt > 0 {
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            check_cancelled(cancel)?;
+            let id = self.id_generator.next_id();
+            let query = self.query_builder.build(domain_name, record_type, id);
+            let Some(mut response) =
+                self.transport
+                    .send_and_recv(server, &query, self.timeout, max_size)?
+            else {
+                continue;
+            };
+            // a response that fills the buffer may have been cut off at its end, so ask again
+            // with room for the largest message there can be
+            if response.len() == max_size && max_size < MAX_MESSAGE_SIZE {
+                max_size = MAX_MESSAGE_SIZE;
+                check_cancelled(cancel)?;
+                let Some(larger) =
+                    self.transport
+                        .send_and_recv(server, &query, self.timeout, max_size)?
+                else {
+                    continue;
+                };
+                response = larger;
+            }
+            if response.len() < 2 || response[..2] != id.to_be_bytes() {
+                color_eyre::eyre::bail!("Response id doesn't match the query");
+            }
+            let response = if self.lenient_validation {
+                Response::parse_lenient(&response)
+            } else {
+                Response::parse(&response)
+            }
+            .context("Failed to parse response")?;
+            let class = self.query_builder.query_class();
+            response.verify_question(&Question::new(domain_name, record_type, class))?;
+            if let Some(client) = self.query_builder.client_cookie() {
+                response.verify_cookie(client)?;
+            }
+            return Ok(response);
+        }
+        color_eyre::eyre::bail!("No response received after {} attempts", self.retries + 1)
+    }
+
+    /// Send a query to the DNS-over-HTTPS endpoint at `url` (e.g.
+    /// `https://cloudflare-dns.com/dns-query`) as the body of a POST request, as described in
+    /// [RFC 8484](https://datatracker.ietf.org/doc/html/rfc8484#section-4.1).  The query's id is
+    /// always 0, as that section recommends, so identical queries make identical requests.
+    #[cfg(feature = "doh")]
+    pub fn query_https(
+        &self,
+        url: &str,
+        domain_name: &str,
+        record_type: QueryType,
+    ) -> color_eyre::Result<Response> {
+        let query = self.query_builder.build(domain_name, record_type, 0);
+        let response = self
+            .https_agent()
+            .post(url)
+            .set("accept", DNS_MESSAGE)
+            .set("content-type", DNS_MESSAGE)
+            .send_bytes(&query);
+        self.read_https_response(response, domain_name, record_type)
+    }
+
+    /// Like `query_https`, but sends the query base64url-encoded in the `dns` parameter of a GET
+    /// request, which HTTP caches along the way can answer.
+    #[cfg(feature = "doh")]
+    pub fn query_https_get(
+        &self,
+        url: &str,
+        domain_name: &str,
+        record_type: QueryType,
+    ) -> color_eyre::Result<Response> {
+        use base64::prelude::*;
+
+        let query = self.query_builder.build(domain_name, record_type, 0);
+        let response = self
+            .https_agent()
+            .get(url)
+            .query("dns", &BASE64_URL_SAFE_NO_PAD.encode(&query))
+            .set("accept", DNS_MESSAGE)
+            .call();
+        self.read_https_response(response, domain_name, record_type)
+    }
+
+    #[cfg(feature = "doh")]
+    fn https_agent(&self) -> ureq::Agent {
+        ureq::AgentBuilder::new().timeout(self.timeout).build()
+    }
+
+    /// Check the HTTP response to a query carries a DNS message, and parse it.
+    #[cfg(feature = "doh")]
+    fn read_https_response(
+        &self,
+        response: Result<ureq::Response, ureq::Error>,
+        domain_name: &str,
+        record_type: QueryType,
+    ) -> color_eyre::Result<Response> {
+        use std::io::Read;
+
+        let response = response.context("DNS-over-HTTPS request failed")?;
+        if response.content_type() != DNS_MESSAGE {
+            color_eyre::eyre::bail!(
+                "Server sent {} instead of a DNS message",
+                response.content_type()
+            );
+        }
+        let mut message = vec![];
+        response
+            .into_reader()
+            .take(u16::MAX as u64 + 1)
+            .read_to_end(&mut message)
+            .context("Failed to read response body")?;
+        if message.len() < 2 || message[..2] != [0, 0] {
+            color_eyre::eyre::bail!("Response id doesn't match the query");
+        }
+        let response = if self.lenient_validation {
+            Response::parse_lenient(&message)
+        } else {
+            Response::parse(&message)
+        }
+        .context("Failed to parse response")?;
+        let class = self.query_builder.query_class();
+        response.verify_question(&Question::new(domain_name, record_type, class))?;
+        Ok(response)
+    }
+
+    /// resolve a dns query, starting from the first upstream server (by default, a random root
+    /// server)
+    pub fn resolve(&self, domain_name: &str, record_type: QueryType) -> color_eyre::Result<Record> {
+        let records = self.resolve_all(domain_name, record_type)?;
+        Ok(records.into_iter().next().unwrap())
+    }
+
+    /// resolve a dns query like `resolve`, returning every record of `record_type` in the answer
+    /// rather than just the first.  The result is never empty.
+    pub fn resolve_all(
+        &self,
+        domain_name: &str,
+        record_type: QueryType,
+    ) -> color_eyre::Result<Vec<Record>> {
+        self.resolve_with_cancel(domain_name, record_type, None, 0)
+    }
+
+    /// resolve a dns query like `resolve_all`, then sort and/or deduplicate the records by their
+    /// data as `options` asks, so the result is stable across queries.
+    pub fn resolve_all_with_options(
+        &self,
+        domain_name: &str,
+        record_type: QueryType,
+        options: ResolveOptions,
+    ) -> color_eyre::Result<Vec<Record>> {
+        let mut records = self.resolve_all(domain_name, record_type)?;
+        if options.sort {
+            records.sort_by_cached_key(Record::data);
+        }
+        if options.dedup {
+            let mut seen = HashSet::new();
+            records.retain(|record| seen.insert(record.data()));
+        }
+        Ok(records)
+    }
+
+    /// resolve a dns query like `resolve`, giving up with `DnsError::Cancelled` once `cancel` is
+    /// set.  The flag is checked between hops and before each attempt of a query, so resolution
+    /// stops once the current attempt times out or is answered.
+    pub fn resolve_cancellable(
+        &self,
+        domain_name: &str,
+        record_type: QueryType,
+        cancel: &AtomicBool,
+    ) -> color_eyre::Result<Record> {
+        let records = self.resolve_with_cancel(domain_name, record_type, Some(cancel), 0)?;
+        Ok(records.into_iter().next().unwrap())
+    }
+
+    fn resolve_with_cancel(
+        &self,
+        domain_name: &str,
+        record_type: QueryType,
+        cancel: Option<&AtomicBool>,
+        depth: usize,
+    ) -> color_eyre::Result<Vec<Record>> {
+        if depth > self.max_depth {
+            return Err(DnsError::TooDeep {
+                name: domain_name.to_string(),
+            }
+            .into());
+        }
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().unwrap();
+            if let Some(records) = cache
+                .get(domain_name, record_type)
+                .filter(|records| !records.is_empty())
+            {
+                return Ok(records.to_vec());
+            }
+        }
+        if self.servers.is_empty() {
+            color_eyre::eyre::bail!("No upstream servers configured");
+        }
+        // the servers to try, in order, until one answers
+        let mut nameservers: Vec<_> = self
+            .servers
+            .iter()
+            .copied()
+            .map(Nameserver::Address)
+            .collect();
+        // the zone the current nameserver is authoritative for
+        let mut zone = String::new();
+        let mut query_result: Vec<Record> = vec![];
+        // every (zone, nameserver set) referred to so far, to detect referral loops
+        let mut referrals = HashSet::new();
+        loop {
+            check_cancelled(cancel)?;
+            let mut response =
+                self.query_any(&nameservers, domain_name, record_type, cancel, depth)?;
+            if response.has_cname_coexistence_error() {
+                self.warnings
+                    .lock()
+                    .unwrap()
+                    .push(ResolveWarning::CnameCoexistence {
+                        name: domain_name.to_string(),
+                    });
+            }
+            let bits = response.header().reserved_bits();
+            if bits != 0 {
+                self.warnings
+                    .lock()
+                    .unwrap()
+                    .push(ResolveWarning::ReservedBits {
+                        name: domain_name.to_string(),
+                        bits,
+                    });
+            }
+            if self.strict_bailiwick {
+                #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                let discarded = response.discard_out_of_bailiwick(&zone);
+                #[cfg(feature = "tracing")]
+                for record in discarded {
+                    tracing::debug!(
+                        name = %record.name,
+                        ty = record.ty.name(),
+                        zone = %zone,
+                        "discarding record outside of zone"
+                    );
+                }
+            }
+            if let Some(referral) = response.authorities().find_map(|record| match record.ty {
+                QueryResponse::Ns(_) => Some(record.name.clone()),
+                _ => None,
+            }) {
+                zone = referral;
+            }
+            query_result = response
+                .answers_for(domain_name, record_type)
+                .cloned()
+                .collect();
+            if !query_result.is_empty() {
+                break;
+            }
+
+            let ns_names = match response.classify() {
+                ResponseKind::Error(ResponseCode::NxDomain) => {
+                    return Err(DnsError::NxDomain {
+                        name: domain_name.to_string(),
+                    }
+                    .into())
+                }
+                ResponseKind::Referral { ns } => ns,
+                _ => break,
+            };
+            let ns_names: Vec<_> = ns_names.iter().map(String::as_str).collect();
+            let mut referral: Vec<_> = ns_names
+                .iter()
+                .map(|name| name.to_ascii_lowercase())
+                .collect();
+            referral.sort();
+            if !referrals.insert((zone.to_ascii_lowercase(), referral)) {
+                return Err(DnsError::Loop { zone }.into());
+            }
+
+            // prefer the servers we were given addresses for, only looking up the others if none
+            // of those answer
+            let glue = self.find_glue(&response, &ns_names);
+            nameservers = glue
+                .into_iter()
+                .map(|address| Nameserver::Address((address, self.nameserver_port).into()))
+                .chain(
+                    ns_names
+                        .iter()
+                        .map(|name| Nameserver::Name(name.to_string())),
+                )
+                .collect();
+        }
+        if query_result.is_empty() {
+            return Err(DnsError::NoData {
+                name: domain_name.to_string(),
+                ty: record_type,
+            }
+            .into());
+        }
+        if let Some(cache) = &self.cache {
+            cache
+                .lock()
+                .unwrap()
+                .insert(domain_name, record_type, query_result.clone());
+        }
+        Ok(query_result)
+    }
+
+    /// resolve the A records of `domain_name` to their addresses
+    pub fn resolve_a(&self, domain_name: &str) -> color_eyre::Result<Vec<Ipv4Addr>> {
+        self.resolve_all(domain_name, QueryType::A)?
+            .into_iter()
+            .map(|record| match record.ty {
+                QueryResponse::A(address) => Ok(address),
+                ty => color_eyre::eyre::bail!("Expected A record, got {}", ty.name()),
+            })
+            .collect()
+    }
+
+    /// resolve the AAAA records of `domain_name` to their addresses
+    pub fn resolve_aaaa(&self, domain_name: &str) -> color_eyre::Result<Vec<Ipv6Addr>> {
+        self.resolve_all(domain_name, QueryType::Aaaa)?
+            .into_iter()
+            .map(|record| match record.ty {
+                QueryResponse::Aaaa(address) => Ok(address),
+                ty => color_eyre::eyre::bail!("Expected AAAA record, got {}", ty.name()),
+            })
+            .collect()
+    }
+
+    /// Resolve a dns query and compare the data of the answers against `expected`, ignoring
+    /// order.
+    pub fn resolve_expect(
+        &self,
+        domain_name: &str,
+        record_type: QueryType,
+        expected: &[&str],
+    ) -> color_eyre::Result<AnswerCheck> {
+        let actual: Vec<_> = self
+            .resolve_all(domain_name, record_type)?
+            .iter()
+            .map(Record::data)
+            .collect();
+        let mut missing: Vec<_> = expected
+            .iter()
+            .filter(|data| !actual.iter().any(|actual| actual == *data))
+            .map(|data| data.to_string())
+            .collect();
+        let mut unexpected: Vec<_> = actual
+            .into_iter()
+            .filter(|data| !expected.contains(&data.as_str()))
+            .collect();
+        missing.sort();
+        unexpected.sort();
+        Ok(AnswerCheck {
+            missing,
+            unexpected,
+        })
+    }
+
+    /// Resolve each of `names` in parallel, with at most `concurrency` resolutions in flight at
+    /// once.  Results are returned in the same order as `names`.
+    pub fn resolve_many(
+        &self,
+        names: &[&str],
+        record_type: QueryType,
+    ) -> Vec<(String, color_eyre::Result<Record>)> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new((0..names.len()).map(|_| None).collect::<Vec<_>>());
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(names.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(name) = names.get(index) else {
+                        break;
+                    };
+                    let result = self.resolve(name, record_type);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+        names
+            .iter()
+            .zip(results.into_inner().unwrap())
+            .map(|(name, result)| (name.to_string(), result.unwrap()))
+            .collect()
+    }
+
+    /// Resolve `domain_name` as each of `record_types` in turn, returning the first record found.
+    /// A type is only skipped if the name has no records of that type; any other error is
+    /// returned immediately.
+    pub fn resolve_preferred(
+        &self,
+        domain_name: &str,
+        record_types: &[QueryType],
+    ) -> color_eyre::Result<Record> {
+        for &record_type in record_types {
+            match self.resolve(domain_name, record_type) {
+                Err(e) if matches!(e.downcast_ref(), Some(DnsError::NoData { .. })) => continue,
+                result => return result,
+            }
+        }
+        color_eyre::eyre::bail!("No records of types {record_types:?} found for {domain_name}")
+    }
+
+    /// Resolve the IPv6 and IPv4 addresses of `host`, pairing each with `port`.  IPv6
+    /// addresses come first.
+    pub fn resolve_socket_addrs(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> color_eyre::Result<Vec<SocketAddr>> {
+        let mut addresses = vec![];
+        let mut errors = vec![];
+        for record_type in [QueryType::Aaaa, QueryType::A] {
+            match self.resolve_all(host, record_type) {
+                Ok(records) => {
+                    for record in records {
+                        match record.ty {
+                            QueryResponse::A(address) => addresses.push((address, port).into()),
+                            QueryResponse::Aaaa(address) => addresses.push((address, port).into()),
+                            _ => {}
+                        }
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        if addresses.is_empty() {
+            if let Some(e) = errors.pop() {
+                return Err(e).context(format!("Unable to resolve {host}"));
+            }
+            color_eyre::eyre::bail!("Unable to resolve {host}");
+        }
+        Ok(addresses)
+    }
+
+    /// Resolve `host` and connect to it over TCP.  All of its addresses are tried at once, and
+    /// the first connection to be established is returned.
+    pub fn connect_tcp(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+    ) -> color_eyre::Result<TcpStream> {
+        let addresses = self.resolve_socket_addrs(host, port)?;
+        let (sender, receiver) = mpsc::channel();
+        for address in &addresses {
+            let sender = sender.clone();
+            let address = *address;
+            thread::spawn(move || {
+                let _ = sender.send(TcpStream::connect_timeout(&address, timeout));
+            });
+        }
+        drop(sender);
+
+        let mut last_error = None;
+        for result in receiver {
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match last_error {
+            Some(e) => Err(e).context(format!("Unable to connect to {host}:{port}")),
+            None => color_eyre::eyre::bail!("Unable to connect to {host}:{port}"),
+        }
+    }
+
+    /// Transfer the zone `zone` from the server at `address` over TCP, returning every record
+    /// in the order sent: the zone's SOA, its other records, and the SOA again.
+    pub fn axfr<A>(&self, address: A, zone: &str) -> color_eyre::Result<Vec<Record>>
+    where
+        A: ToSocketAddrs,
+    {
+        let Some(server) = address
+            .to_socket_addrs()
+            .context("Unable to resolve server address")?
+            .next()
+        else {
+            color_eyre::eyre::bail!("Server address resolved to nothing");
+        };
+        let mut stream = TcpStream::connect_timeout(&server, self.timeout)
+            .with_context(|| format!("Unable to connect to {server}"))?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .context("Unable to set socket timeout")?;
+        let id = self.id_generator.next_id();
+        let query = self.query_builder.build(zone, QueryType::Axfr, id);
+        write_tcp_message(&mut stream, &query).context("Failed to send query to server")?;
+
+        let mut records: Vec<Record> = vec![];
+        loop {
+            let message = read_tcp_message(&mut stream).context("Zone transfer ended early")?;
+            if message.len() < 2 || message[..2] != id.to_be_bytes() {
+                color_eyre::eyre::bail!("Response id doesn't match the query");
+            }
+            let response = if self.lenient_validation {
+                Response::parse_lenient(&message)
+            } else {
+                Response::parse(&message)
+            }
+            .context("Failed to parse response")?;
+            let code = response.response_code();
+            if code != ResponseCode::NoError {
+                color_eyre::eyre::bail!("Zone transfer of {zone} refused ({code:?})");
+            }
+            if records.is_empty() {
+                let class = self.query_builder.query_class();
+                response.verify_question(&Question::new(zone, QueryType::Axfr, class))?;
+            }
+            for record in response.answers() {
+                let is_soa = matches!(record.ty, QueryResponse::Soa(_));
+                if records.is_empty() && !is_soa {
+                    color_eyre::eyre::bail!("Zone transfer of {zone} didn't start with its SOA");
+                }
+                records.push(record.clone());
+                // the transfer ends with a second copy of the SOA it started with
+                if is_soa && records.len() > 1 {
+                    return Ok(records);
+                }
+            }
+        }
+    }
+
+    /// Query each of `nameservers` in turn until one of them answers, looking up the addresses
+    /// of those given by name as they're reached.  Fails with the last error if none answer.
+    fn query_any(
+        &self,
+        nameservers: &[Nameserver],
+        domain_name: &str,
+        record_type: QueryType,
+        cancel: Option<&AtomicBool>,
+        depth: usize,
+    ) -> color_eyre::Result<Response> {
+        let mut last_error = None;
+        for nameserver in nameservers {
+            let address = match nameserver {
+                Nameserver::Address(address) => *address,
+                Nameserver::Name(name) => match self.nameserver_address(name, cancel, depth) {
+                    Ok(address) => (address, self.nameserver_port).into(),
+                    // every other nameserver would give up in the same way
+                    Err(e)
+                        if matches!(
+                            e.downcast_ref(),
+                            Some(DnsError::Cancelled | DnsError::TooDeep { .. })
+                        ) =>
+                    {
+                        return Err(e)
+                    }
+                    Err(e) => {
+                        last_error = Some(e);
+                        continue;
+                    }
+                },
+            };
+            #[cfg(feature = "tracing")]
+            tracing::info!(%address, name = domain_name, ty = ?record_type, "querying nameserver");
+            match self.query_server_with_cancel(address, domain_name, record_type, cancel) {
+                Ok(response) => return Ok(response),
+                Err(e) if matches!(e.downcast_ref(), Some(DnsError::Cancelled)) => return Err(e),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| color_eyre::eyre::eyre!("No nameservers to query")))
+    }
+
+    /// Look up the address of the nameserver `name`, falling back to its IPv6 address if it has
+    /// no IPv4 one, as on IPv6-only paths.
+    fn nameserver_address(
+        &self,
+        name: &str,
+        cancel: Option<&AtomicBool>,
+        depth: usize,
+    ) -> color_eyre::Result<IpAddr> {
+        for record_type in [QueryType::A, QueryType::Aaaa] {
+            match self.resolve_with_cancel(name, record_type, cancel, depth + 1) {
+                Ok(records) => {
+                    let address = records.iter().find_map(|record| match record.ty {
+                        QueryResponse::A(address) => Some(IpAddr::from(address)),
+                        QueryResponse::Aaaa(address) => Some(IpAddr::from(address)),
+                        _ => None,
+                    });
+                    if let Some(address) = address {
+                        return Ok(address);
+                    }
+                }
+                Err(e) if matches!(e.downcast_ref(), Some(DnsError::NoData { .. })) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        color_eyre::eyre::bail!("Nameserver {name} has no addresses")
+    }
+
+    /// Find the glue addresses of `ns_names` among the first `max_additionals_scanned` additional
+    /// records, IPv4 addresses first.
+    fn find_glue(&self, response: &Response, ns_names: &[&str]) -> Vec<IpAddr> {
+        let mut glue: Vec<_> = response
+            .additionals()
+            .take(self.max_additionals_scanned)
+            .filter(|record| ns_names.iter().any(|name| record.has_name(name)))
+            .filter_map(|record| match record.ty {
+                QueryResponse::A(address) => Some(IpAddr::from(address)),
+                QueryResponse::Aaaa(address) => Some(IpAddr::from(address)),
+                _ => None,
+            })
+            .collect();
+        glue.sort_by_key(IpAddr::is_ipv6);
+        glue
+    }
+}
+
+/// Fail with `DnsError::Cancelled` if `cancel` is set
+fn check_cancelled(cancel: Option<&AtomicBool>) -> color_eyre::Result<()> {
+    if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+        return Err(DnsError::Cancelled.into());
+    }
+    Ok(())
+}
+
+/// A nameserver `resolve` may send its next query to
+enum Nameserver {
+    Address(SocketAddr),
+    /// a nameserver without glue, whose address must be looked up first
+    Name(String),
+}
+
+/// How `resolve_all_with_options` post-processes the resolved records
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolveOptions {
+    /// sort the records by their data
+    pub sort: bool,
+    /// keep only the first of any records with the same data
+    pub dedup: bool,
+}
+
+/// The result of comparing resolved answers against the expected ones, from `resolve_expect`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnswerCheck {
+    /// expected answers that weren't resolved
+    pub missing: Vec<String>,
+    /// resolved answers that weren't expected
+    pub unexpected: Vec<String>,
+}
+
+impl AnswerCheck {
+    /// Whether the resolved answers were exactly the expected ones
+    pub fn matches(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+impl Display for AnswerCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.matches() {
+            return write!(f, "answers match");
+        }
+        let mut lines = vec![];
+        lines.extend(self.missing.iter().map(|data| format!("missing: {data}")));
+        lines.extend(
+            self.unexpected
+                .iter()
+                .map(|data| format!("unexpected: {data}")),
+        );
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        decode_dns_name, encode_dns_name, AsBytes, ClassType, MockTransport, Soa, TcpTransport,
+    };
+    use std::{
+        net::{TcpListener, UdpSocket},
+        sync::{atomic::AtomicU16, Arc},
+        time::Instant,
+    };
+
+    /// Builds the wire format of a response to `query`, echoing its id and question.
+    fn response_bytes(
+        query: &[u8],
+        answers: &[Record],
+        authorities: &[Record],
+        additionals: &[Record],
+    ) -> Vec<u8> {
+        let mut output = query[..2].to_vec();
+        output.extend_from_slice(&[0x81, 0x80, 0x00, 0x01]);
+        for section in [answers, authorities, additionals] {
+            output.extend_from_slice(&(section.len() as u16).to_be_bytes());
+        }
+        output.extend_from_slice(&query[12..]);
+        for record in answers.iter().chain(authorities).chain(additionals) {
+            record.as_bytes(&mut output);
+        }
+        output
+    }
+
+    /// Builds the SOA record of `zone`, with its rdata in wire format
+    fn soa_record(zone: &str) -> Record {
+        let soa = Soa {
+            mname: format!("ns.{zone}"),
+            rname: format!("admin.{zone}"),
+            serial: 1,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 300,
+        };
+        let mut data = encode_dns_name(&soa.mname);
+        data.extend(encode_dns_name(&soa.rname));
+        for field in [soa.serial, soa.refresh, soa.retry, soa.expire, soa.minimum] {
+            data.extend(field.to_be_bytes());
+        }
+        Record {
+            name: zone.into(),
+            ty: QueryResponse::Soa(soa),
+            class: ClassType::IN,
+            ttl: 3600,
+            data,
+        }
+    }
+
+    /// An A record for `name`, pointing at 192.0.2.`octet`
+    fn a_record(name: &str, octet: u8) -> Record {
+        Record::new(
+            name,
+            QueryResponse::a([192, 0, 2, octet]),
+            ClassType::IN,
+            60,
+        )
+    }
+
+    /// A referral of `zone` to the nameserver `ns`: the NS record, and a glue record giving its
+    /// address if there is one.
+    fn referral(zone: &str, ns: &str, glue: Option<IpAddr>) -> (Record, Option<Record>) {
+        let ns_record = Record::new(zone, QueryResponse::Ns(ns.into()), ClassType::IN, 60);
+        let glue = glue.map(|address| Record::new(ns, address.into(), ClassType::IN, 60));
+        (ns_record, glue)
+    }
+
+    /// Spawns a server on loopback that passes `count` queries to `handler`, sending back
+    /// whatever response it returns.
+    fn serve<F>(count: usize, mut handler: F) -> SocketAddr
+    where
+        F: FnMut(&[u8]) -> Option<Vec<u8>> + Send + 'static,
+    {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            for _ in 0..count {
+                let (size, peer) = socket.recv_from(&mut buf).unwrap();
+                if let Some(response) = handler(&buf[..size]) {
+                    socket.send_to(&response, peer).unwrap();
+                }
+            }
+        });
+        address
+    }
+
+    /// Spawns a server on loopback that answers a single query with `answers`.
+    fn serve_answers(answers: Vec<Record>) -> SocketAddr {
+        serve(1, move |query| {
+            Some(response_bytes(query, &answers, &[], &[]))
+        })
+    }
+
+    #[test]
+    fn test_fixed_id_query() {
+        let resolver = Resolver::new().with_id_generator(FixedId(0x1234));
+        let query = resolver.build_query("google.com", QueryType::A);
+
+        assert_eq!(query, b"\x12\x34\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x06google\x03com\x00\x00\x01\x00\x01");
+        assert_eq!(query, resolver.build_query("google.com", QueryType::A));
+    }
+
+    /// Builds a referral response with `count` junk TXT additionals, with an A record for
+    /// `ns.example` spliced in at `glue_index`.
+    fn referral_with_additionals(count: u16, glue_index: u16) -> Response {
+        let mut input = vec![0x00, 0x01, 0x81, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00];
+        input.extend_from_slice(&count.to_be_bytes());
+        Question::new("example", QueryType::A, ClassType::IN).as_bytes(&mut input);
+        for i in 0..count {
+            let record = if i == glue_index {
+                Record {
+                    name: "ns.example".into(),
+                    ty: QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+                    class: ClassType::IN,
+                    ttl: 60,
+                    data: vec![192, 0, 2, 1],
+                }
+            } else {
+                Record {
+                    name: "junk.example".into(),
+                    ty: QueryResponse::Txt("junk".into()),
+                    class: ClassType::IN,
+                    ttl: 60,
+                    data: b"junk".to_vec(),
+                }
+            };
+            record.as_bytes(&mut input);
+        }
+        Response::parse(&input).unwrap()
+    }
+
+    #[test]
+    fn test_max_additionals_scanned() {
+        let resolver = Resolver::new();
+
+        let glue = [IpAddr::from(Ipv4Addr::new(192, 0, 2, 1))];
+        let response = referral_with_additionals(1000, 10);
+        assert_eq!(resolver.find_glue(&response, &["ns.example"]), glue);
+
+        let response = referral_with_additionals(1000, 999);
+        assert!(resolver.find_glue(&response, &["ns.example"]).is_empty());
+        assert_eq!(
+            resolver
+                .with_max_additionals_scanned(1000)
+                .find_glue(&response, &["ns.example"]),
+            glue
+        );
+    }
+
+    #[test]
+    fn test_large_buffer_size() {
+        let answers: Vec<_> = (0..20)
+            .map(|_| Record {
+                name: "example".into(),
+                ty: QueryResponse::Txt("x".repeat(100)),
+                class: ClassType::IN,
+                ttl: 60,
+                data: "x".repeat(100).into_bytes(),
+            })
+            .collect();
+        let server = serve_answers(answers.clone());
+
+        let response = Resolver::new()
+            .with_buffer_size(4096)
+            .query_server(server, "example", QueryType::Txt)
+            .unwrap();
+        assert_eq!(response.answers().cloned().collect::<Vec<_>>(), answers);
+    }
+
+    #[test]
+    fn test_response_filling_buffer() {
+        let answers: Vec<_> = (0..20)
+            .map(|_| {
+                Record::new(
+                    "example",
+                    QueryResponse::Txt("x".repeat(100)),
+                    ClassType::IN,
+                    60,
+                )
+            })
+            .collect();
+        let expected = answers.clone();
+        // the first response is cut off at the default buffer size; the second fits
+        let server = serve(2, move |query| {
+            Some(response_bytes(query, &answers, &[], &[]))
+        });
+
+        let response = Resolver::new()
+            .with_retries(0)
+            .query_server(server, "example", QueryType::Txt)
+            .unwrap();
+        assert_eq!(response.answers().cloned().collect::<Vec<_>>(), expected);
+    }
+
+    /// Hands out consecutive ids, starting from zero.
+    #[derive(Default)]
+    struct SequentialIds(AtomicU16);
+
+    impl IdGenerator for SequentialIds {
+        fn next_id(&self) -> u16 {
+            self.0.fetch_add(1, Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn test_retry_after_timeout() {
+        let (ids, received_ids) = mpsc::channel();
+        let mut dropped = false;
+        let server = serve(2, move |query| {
+            ids.send(u16::from_be_bytes([query[0], query[1]])).unwrap();
+            // drop the first attempt, answer the second
+            if !dropped {
+                dropped = true;
+                return None;
+            }
+            Some(response_bytes(query, &[], &[], &[]))
+        });
+
+        let response = Resolver::new()
+            .with_id_generator(SequentialIds::default())
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(1)
+            .query_server(server, "example", QueryType::A);
+        assert!(response.is_ok());
+        assert_eq!(received_ids.try_iter().collect::<Vec<_>>(), [0, 1]);
+    }
+
+    #[test]
+    fn test_ignore_stale_response() {
+        // answer the first attempt only once the second has been sent, with the first's id
+        let mut first_query = None;
+        let server = serve(2, move |query| match first_query.take() {
+            None => {
+                first_query = Some(query.to_vec());
+                None
+            }
+            Some(first) => Some(response_bytes(&first, &[], &[], &[])),
+        });
+
+        let response = Resolver::new()
+            .with_id_generator(SequentialIds::default())
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(1)
+            .query_server(server, "example", QueryType::A);
+        assert!(response.is_err());
+    }
+
+    #[test]
+    fn test_ignore_response_from_other_address() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = socket.local_addr().unwrap();
+        let spoofer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let forged = Record::new(
+            "example",
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 66)),
+            ClassType::IN,
+            60,
+        );
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (size, peer) = socket.recv_from(&mut buf).unwrap();
+            // a forged response with the right id arrives first, but from the wrong address
+            let response = response_bytes(&buf[..size], &[forged], &[], &[]);
+            spoofer.send_to(&response, peer).unwrap();
+            let response = response_bytes(&buf[..size], &[], &[], &[]);
+            socket.send_to(&response, peer).unwrap();
+        });
+
+        let response = Resolver::new()
+            .query_server(server, "example", QueryType::A)
+            .unwrap();
+        assert_eq!(response.answers().count(), 0);
+    }
+
+    #[test]
+    fn test_reject_mismatched_question() {
+        // echo the query back for a different name, keeping its id
+        let server = serve(1, |query| {
+            let other = QueryBuilder::new().build("other.example", QueryType::A, 0);
+            let mut response = response_bytes(&other, &[], &[], &[]);
+            response[..2].copy_from_slice(&query[..2]);
+            Some(response)
+        });
+
+        let err = Resolver::new()
+            .query_server(server, "example", QueryType::A)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DnsError::QuestionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reject_mismatched_cookie() {
+        // echo the query back as the response, OPT record and all, with a wrong client cookie
+        let server = serve(1, |query| {
+            let mut response = query.to_vec();
+            response[2] |= 0x80;
+            *response.last_mut().unwrap() ^= 0xff;
+            Some(response)
+        });
+
+        let resolver = Resolver::new()
+            .with_retries(0)
+            .with_query_builder(QueryBuilder::new().cookie([7; 8], &[]));
+        let err = resolver
+            .query_server(server, "example", QueryType::A)
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DnsError::BadCookie)));
+    }
+
+    #[test]
+    fn test_socket_reuse() {
+        let (peers, received_peers) = mpsc::channel();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            for _ in 0..2 {
+                let (size, peer) = socket.recv_from(&mut buf).unwrap();
+                peers.send(peer).unwrap();
+                let response = response_bytes(&buf[..size], &[], &[], &[]);
+                socket.send_to(&response, peer).unwrap();
+            }
+        });
+
+        let resolver = Resolver::new().with_servers(&[server]);
+        assert!(resolver.local_ports().is_empty());
+        assert!(resolver.query("example", QueryType::A).is_ok());
+        assert!(resolver.query("example", QueryType::Aaaa).is_ok());
+        let port = received_peers.recv().unwrap().port();
+        assert_eq!(received_peers.recv().unwrap().port(), port);
+        assert_eq!(resolver.local_ports(), [port]);
+    }
+
+    #[test]
+    fn test_local_address() {
+        let (peers, received_peers) = mpsc::channel();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (size, peer) = socket.recv_from(&mut buf).unwrap();
+            peers.send(peer).unwrap();
+            let response = response_bytes(&buf[..size], &[], &[], &[]);
+            socket.send_to(&response, peer).unwrap();
+        });
+
+        let local = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        let resolver = Resolver::new()
+            .with_servers(&[server])
+            .with_local_address(local);
+        assert!(resolver.query("example", QueryType::A).is_ok());
+        assert_eq!(received_peers.recv().unwrap().ip(), local);
+    }
+
+    #[test]
+    fn test_query_any_of() {
+        let answer = Record::new(
+            "example",
+            QueryResponse::A(Ipv4Addr::LOCALHOST),
+            ClassType::IN,
+            60,
+        );
+        // nothing listens on the first server, so only the second answers
+        let dead = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let live = serve_answers(vec![answer.clone()]);
+
+        let resolver = Resolver::new()
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(0);
+        let response = resolver
+            .query_any_of(&[dead, live], "example", QueryType::A)
+            .unwrap();
+        assert_eq!(response.answers().collect::<Vec<_>>(), [&answer]);
+
+        let err = resolver
+            .query_any_of(&[dead], "example", QueryType::A)
+            .unwrap_err();
+        assert!(err.to_string().contains(&dead.to_string()), "{err}");
+    }
+
+    #[test]
+    fn test_connect_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let dns = serve(2, |query| {
+            // answer A queries with loopback, and anything else with no records
+            let answers = match query[query.len() - 4..query.len() - 2] {
+                [0, 1] => vec![Record {
+                    name: "app.test".into(),
+                    ty: QueryResponse::A(Ipv4Addr::LOCALHOST),
+                    class: ClassType::IN,
+                    ttl: 60,
+                    data: vec![127, 0, 0, 1],
+                }],
+                _ => vec![],
+            };
+            Some(response_bytes(query, &answers, &[], &[]))
+        });
+
+        let stream = Resolver::new()
+            .with_servers(&[dns])
+            .connect_tcp("app.test", port, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+    }
+
+    #[test]
+    fn test_connect_tcp_tries_every_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let dns = serve(4, |query| {
+            // nothing listens on the first address, only on the second
+            let answers = match query[query.len() - 4..query.len() - 2] {
+                [0, 1] => [[127, 0, 0, 2], [127, 0, 0, 1]]
+                    .map(|octets| {
+                        Record::new("app.test", QueryResponse::a(octets), ClassType::IN, 60)
+                    })
+                    .to_vec(),
+                _ => vec![],
+            };
+            Some(response_bytes(query, &answers, &[], &[]))
+        });
+        let resolver = Resolver::new().with_servers(&[dns]);
+
+        let addresses = resolver.resolve_socket_addrs("app.test", port).unwrap();
+        assert_eq!(
+            addresses,
+            [
+                SocketAddr::from(([127, 0, 0, 2], port)),
+                SocketAddr::from(([127, 0, 0, 1], port))
+            ]
+        );
+        let stream = resolver
+            .connect_tcp("app.test", port, Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+    }
+
+    #[test]
+    fn test_resolve_preferred() {
+        let dns = serve(2, |query| {
+            // only A records exist, so AAAA queries get NODATA
+            let answers = match query[query.len() - 4..query.len() - 2] {
+                [0, 1] => vec![Record {
+                    name: "v4only.test".into(),
+                    ty: QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+                    class: ClassType::IN,
+                    ttl: 60,
+                    data: vec![192, 0, 2, 1],
+                }],
+                _ => vec![],
+            };
+            Some(response_bytes(query, &answers, &[], &[]))
+        });
+
+        let record = Resolver::new()
+            .with_servers(&[dns])
+            .resolve_preferred("v4only.test", &[QueryType::Aaaa, QueryType::A])
+            .unwrap();
+        assert_eq!(record.ty, QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)));
+    }
+
+    #[test]
+    fn test_referral_loop() {
+        let dns = serve(2, |query| {
+            let (ns, glue) = referral(
+                "loop.test",
+                "ns.loop.test",
+                Some(Ipv4Addr::LOCALHOST.into()),
+            );
+            Some(response_bytes(query, &[], &[ns], glue.as_slice()))
+        });
+
+        let error = Resolver::new()
+            .with_servers(&[dns])
+            .with_nameserver_port(dns.port())
+            .resolve("www.loop.test", QueryType::A)
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(DnsError::Loop { zone }) if zone == "loop.test"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_cancellable() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let handler_cancel = cancel.clone();
+        // refers the resolver back to itself, cancelling once the first hop is answered
+        let dns = serve(1, move |query| {
+            handler_cancel.store(true, Ordering::Relaxed);
+            let address = Ipv4Addr::LOCALHOST.into();
+            let (ns, glue) = referral("cancel.test", "ns.cancel.test", Some(address));
+            Some(response_bytes(query, &[], &[ns], glue.as_slice()))
+        });
+
+        let error = Resolver::new()
+            .with_servers(&[dns])
+            .with_nameserver_port(dns.port())
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(0)
+            .resolve_cancellable("www.cancel.test", QueryType::A, &cancel)
+            .unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(DnsError::Cancelled)));
+
+        // cancelling while a server is silent stops the retries, rather than waiting out each of
+        // their timeouts and backoffs
+        let cancel = Arc::new(AtomicBool::new(false));
+        let silent = serve(1, |_| None);
+        let canceller = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                cancel.store(true, Ordering::Relaxed);
+            })
+        };
+        let start = Instant::now();
+        let error = Resolver::new()
+            .with_servers(&[silent])
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(10)
+            .resolve_cancellable("www.cancel.test", QueryType::A, &cancel)
+            .unwrap_err();
+        canceller.join().unwrap();
+        assert!(matches!(error.downcast_ref(), Some(DnsError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_mock_transport_referral() {
+        let query = QueryBuilder::new().build("www.mock.test", QueryType::A, 0);
+        let address = Ipv4Addr::new(192, 0, 2, 53).into();
+        let (ns, glue) = referral("mock.test", "ns.mock.test", Some(address));
+        let answer = Record::new(
+            "www.mock.test",
+            QueryResponse::a([192, 0, 2, 1]),
+            ClassType::IN,
+            60,
+        );
+        let transport = Arc::new(MockTransport::new([
+            response_bytes(&query, &[], &[ns], glue.as_slice()),
+            response_bytes(&query, std::slice::from_ref(&answer), &[], &[]),
+        ]));
+        let root = SocketAddr::from((Ipv4Addr::new(198, 41, 0, 4), 53));
+
+        let record = Resolver::new()
+            .with_servers(&[root])
+            .with_transport(transport.clone())
+            .resolve("www.mock.test", QueryType::A)
+            .unwrap();
+        assert_eq!(record, answer);
+        let servers: Vec<_> = transport
+            .requests()
+            .into_iter()
+            .map(|(server, _)| server)
+            .collect();
+        assert_eq!(
+            servers,
+            [root, SocketAddr::from((Ipv4Addr::new(192, 0, 2, 53), 53))]
+        );
+    }
+
+    #[test]
+    fn test_ipv6_only_nameserver() {
+        let query = |name, ty| QueryBuilder::new().build(name, ty, 0);
+        let ns = Record::new(
+            "v6.test",
+            QueryResponse::Ns("ns.v6.test".into()),
+            ClassType::IN,
+            60,
+        );
+        let address = "2001:db8::53".parse::<Ipv6Addr>().unwrap();
+        let ns_address = Record::new("ns.v6.test", address.into(), ClassType::IN, 60);
+        let answer = Record::new(
+            "www.v6.test",
+            QueryResponse::a([192, 0, 2, 1]),
+            ClassType::IN,
+            60,
+        );
+        let transport = Arc::new(MockTransport::new([
+            // a referral without glue
+            response_bytes(&query("www.v6.test", QueryType::A), &[], &[ns], &[]),
+            // the nameserver has no IPv4 address...
+            response_bytes(
+                &query("ns.v6.test", QueryType::A),
+                &[],
+                &[soa_record("v6.test")],
+                &[],
+            ),
+            // ...but does have an IPv6 one
+            response_bytes(
+                &query("ns.v6.test", QueryType::Aaaa),
+                &[ns_address],
+                &[],
+                &[],
+            ),
+            response_bytes(
+                &query("www.v6.test", QueryType::A),
+                std::slice::from_ref(&answer),
+                &[],
+                &[],
+            ),
+        ]));
+        let root = SocketAddr::from((Ipv4Addr::new(198, 41, 0, 4), 53));
+
+        let record = Resolver::new()
+            .with_servers(&[root])
+            .with_transport(transport.clone())
+            .resolve("www.v6.test", QueryType::A)
+            .unwrap();
+        assert_eq!(record, answer);
+        let (server, _) = transport.requests().pop().unwrap();
+        assert_eq!(server, SocketAddr::from((address, 53)));
+    }
+
+    #[test]
+    fn test_aaaa_only_glue() {
+        let query = QueryBuilder::new().build("www.v6.test", QueryType::A, 0);
+        let address = "2001:db8::53".parse::<Ipv6Addr>().unwrap();
+        let (ns, glue) = referral("v6.test", "ns.v6.test", Some(address.into()));
+        let answer = Record::new(
+            "www.v6.test",
+            QueryResponse::a([192, 0, 2, 1]),
+            ClassType::IN,
+            60,
+        );
+        let transport = Arc::new(MockTransport::new([
+            response_bytes(&query, &[], &[ns], glue.as_slice()),
+            response_bytes(&query, std::slice::from_ref(&answer), &[], &[]),
+        ]));
+        let root = SocketAddr::from((Ipv4Addr::new(198, 41, 0, 4), 53));
+
+        let record = Resolver::new()
+            .with_servers(&[root])
+            .with_transport(transport.clone())
+            .resolve("www.v6.test", QueryType::A)
+            .unwrap();
+        assert_eq!(record, answer);
+        let (server, _) = transport.requests().pop().unwrap();
+        assert_eq!(server, SocketAddr::from((address, 53)));
+    }
+
+    #[test]
+    fn test_referral_tries_each_nameserver() {
+        let mut queries = 0;
+        let dns = serve(2, move |query| {
+            queries += 1;
+            if queries > 1 {
+                let answer = Record::new(
+                    "www.fallback.test",
+                    QueryResponse::a([192, 0, 2, 1]),
+                    ClassType::IN,
+                    60,
+                );
+                return Some(response_bytes(query, &[answer], &[], &[]));
+            }
+            // ns0 has no glue, ns1's glue points at a dead address, and only ns2 answers
+            let (ns, glue): (Vec<_>, Vec<_>) = [
+                ("ns0.other.test", None),
+                (
+                    "ns1.fallback.test",
+                    Some(Ipv4Addr::new(127, 0, 0, 2).into()),
+                ),
+                ("ns2.fallback.test", Some(Ipv4Addr::LOCALHOST.into())),
+            ]
+            .into_iter()
+            .map(|(name, glue)| referral("fallback.test", name, glue))
+            .unzip();
+            let glue: Vec<_> = glue.into_iter().flatten().collect();
+            Some(response_bytes(query, &[], &ns, &glue))
+        });
+
+        let record = Resolver::new()
+            .with_servers(&[dns])
+            .with_nameserver_port(dns.port())
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(0)
+            .resolve("www.fallback.test", QueryType::A)
+            .unwrap();
+        assert_eq!(record.ty, QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        // refer every name to a nameserver named within it, without glue, so each lookup needs
+        // another one nested inside it
+        let dns = serve(4, |query| {
+            let (_, name) = decode_dns_name(&query[12..], query).unwrap();
+            let (ns, _) = referral(&name, &format!("ns.{name}"), None);
+            Some(response_bytes(query, &[], &[ns], &[]))
+        });
+
+        let err = Resolver::new()
+            .with_servers(&[dns])
+            .with_max_depth(3)
+            .resolve("deep.test", QueryType::A)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DnsError::TooDeep { name }) if name == "ns.ns.ns.ns.deep.test"
+        ));
+    }
+
+    /// Answer one DNS-over-HTTP request on a local port with `answers`, decoding the query from
+    /// the body of a POST or the `dns` parameter of a GET.  Returns the endpoint's url, and a
+    /// receiver for the request's method.
+    #[cfg(feature = "doh")]
+    fn serve_https(answers: Vec<Record>) -> (String, mpsc::Receiver<String>) {
+        use base64::prelude::*;
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/dns-query", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_ascii_lowercase();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(length) = line.strip_prefix("content-length: ") {
+                    content_length = length.parse().unwrap();
+                }
+            }
+            let (method, target) = request_line.split_once(' ').unwrap();
+            let query = match target.split_once("?dns=") {
+                Some((_, encoded)) => {
+                    let encoded = encoded.split_once(' ').unwrap().0;
+                    BASE64_URL_SAFE_NO_PAD.decode(encoded).unwrap()
+                }
+                None => {
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    body
+                }
+            };
+            sender.send(method.to_string()).unwrap();
+            let response = response_bytes(&query, &answers, &[], &[]);
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/dns-message\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n",
+                response.len()
+            )
+            .unwrap();
+            stream.write_all(&response).unwrap();
+        });
+        (url, receiver)
+    }
+
+    #[test]
+    #[cfg(feature = "doh")]
+    fn test_query_https() {
+        let answer = Record::new(
+            "example.com",
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+            ClassType::IN,
+            300,
+        );
+        let (url, method) = serve_https(vec![answer.clone()]);
+        let response = Resolver::new()
+            .query_https(&url, "example.com", QueryType::A)
+            .unwrap();
+        assert_eq!(method.recv().unwrap(), "POST");
+        assert_eq!(response.header().id(), 0);
+        assert_eq!(response.answers().collect::<Vec<_>>(), [&answer]);
+
+        let (url, method) = serve_https(vec![answer.clone()]);
+        let response = Resolver::new()
+            .query_https_get(&url, "example.com", QueryType::A)
+            .unwrap();
+        assert_eq!(method.recv().unwrap(), "GET");
+        assert_eq!(response.header().id(), 0);
+        assert_eq!(response.answers().collect::<Vec<_>>(), [&answer]);
+    }
+
+    #[test]
+    fn test_tcp_transport() {
+        let answer = Record::new(
+            "example.com",
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+            ClassType::IN,
+            300,
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap();
+        let answers = vec![answer.clone()];
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = read_tcp_message(&mut stream).unwrap();
+            let response = response_bytes(&query, &answers, &[], &[]);
+            write_tcp_message(&mut stream, &response).unwrap();
+        });
+
+        let response = Resolver::new()
+            .with_transport(TcpTransport::new())
+            .with_timeout(Duration::from_secs(1))
+            .query_server(server, "example.com", QueryType::A)
+            .unwrap();
+        assert_eq!(response.answers().collect::<Vec<_>>(), [&answer]);
+    }
+
+    #[test]
+    fn test_axfr() {
+        let record = |name: &str, ty, data: Vec<u8>| Record {
+            name: name.into(),
+            ty,
+            class: ClassType::IN,
+            ttl: 3600,
+            data,
+        };
+        let soa = soa_record("zone.test");
+        let ns = record(
+            "zone.test",
+            QueryResponse::Ns("ns.zone.test".into()),
+            encode_dns_name("ns.zone.test"),
+        );
+        let a = record(
+            "ns.zone.test",
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 53)),
+            vec![192, 0, 2, 53],
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap();
+        let messages = [vec![soa.clone(), ns.clone()], vec![a.clone(), soa.clone()]];
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = read_tcp_message(&mut stream).unwrap();
+            for answers in messages {
+                let response = response_bytes(&query, &answers, &[], &[]);
+                write_tcp_message(&mut stream, &response).unwrap();
+            }
+        });
+
+        let records = Resolver::new()
+            .with_timeout(Duration::from_secs(1))
+            .axfr(server, "zone.test")
+            .unwrap();
+        assert_eq!(records, [soa.clone(), ns, a, soa]);
+    }
+
+    #[test]
+    fn test_axfr_class() {
+        let mut soa = soa_record("zone.test");
+        soa.class = ClassType::CH;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap();
+        let answers = [soa.clone(), soa.clone()];
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = read_tcp_message(&mut stream).unwrap();
+            let response = response_bytes(&query, &answers, &[], &[]);
+            write_tcp_message(&mut stream, &response).unwrap();
+        });
+
+        let records = Resolver::new()
+            .with_query_builder(QueryBuilder::new().class(ClassType::CH))
+            .with_timeout(Duration::from_secs(1))
+            .axfr(server, "zone.test")
+            .unwrap();
+        assert_eq!(records, [soa.clone(), soa]);
+    }
+
+    #[test]
+    fn test_nxdomain() {
+        let dns = serve(1, |query| {
+            let mut response = response_bytes(query, &[], &[soa_record("test")], &[]);
+            // RCODE 3
+            response[3] |= 0x03;
+            Some(response)
+        });
+
+        let error = Resolver::new()
+            .with_servers(&[dns])
+            .resolve("missing.test", QueryType::A)
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(DnsError::NxDomain { name }) if name == "missing.test"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_many() {
+        let names: Vec<_> = (0..8).map(|i| format!("host{i}.test")).collect();
+        let dns = serve(names.len(), |query| {
+            // answer hostN.test with 192.0.2.N
+            let (_, name) = decode_dns_name(&query[12..], query).unwrap();
+            let host = name.trim_start_matches("host");
+            let octet: u8 = host.split('.').next().unwrap().parse().unwrap();
+            let answer = a_record(&name, octet);
+            Some(response_bytes(query, &[answer], &[], &[]))
+        });
+
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        let results = Resolver::new()
+            .with_servers(&[dns])
+            .with_concurrency(3)
+            .resolve_many(&names, QueryType::A);
+        assert_eq!(results.len(), names.len());
+        for (i, (name, result)) in results.into_iter().enumerate() {
+            assert_eq!(name, names[i]);
+            assert_eq!(
+                result.unwrap().ty,
+                QueryResponse::A(Ipv4Addr::new(192, 0, 2, i as u8))
+            );
+        }
+    }
+
+    #[test]
+    fn test_drain_unsolicited_datagrams() {
+        let (sent_extra, wait_for_extra) = mpsc::channel();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (size, peer) = socket.recv_from(&mut buf).unwrap();
+            let response = response_bytes(&buf[..size], &[], &[], &[]);
+            socket.send_to(&response, peer).unwrap();
+            // a second copy of the response arrives after the first was accepted
+            socket.send_to(&response, peer).unwrap();
+            sent_extra.send(()).unwrap();
+
+            let (size, peer) = socket.recv_from(&mut buf).unwrap();
+            let response = response_bytes(&buf[..size], &[], &[], &[]);
+            socket.send_to(&response, peer).unwrap();
+        });
+
+        // with a fixed id, the extra datagram would otherwise pass for the second response
+        let resolver = Resolver::new()
+            .with_servers(&[server])
+            .with_id_generator(FixedId(7));
+        resolver.query("first.test", QueryType::A).unwrap();
+        wait_for_extra.recv().unwrap();
+        resolver.query("second.test", QueryType::A).unwrap();
+    }
+
+    #[test]
+    fn test_reserved_bits_warning() {
+        let answers = vec![Record::new(
+            "www.test",
+            QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+            ClassType::IN,
+            60,
+        )];
+        let dns = serve(1, move |query| {
+            let mut response = response_bytes(query, &answers, &[], &[]);
+            response[3] |= 0x40;
+            Some(response)
+        });
+
+        let resolver = Resolver::new().with_servers(&[dns]);
+        resolver.resolve("www.test", QueryType::A).unwrap();
+        let warnings = resolver.take_warnings();
+        assert_eq!(
+            warnings,
+            [ResolveWarning::ReservedBits {
+                name: "www.test".into(),
+                bits: 0b100
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "Response for www.test set reserved header bits 0b100"
+        );
+    }
+
+    #[test]
+    fn test_cname_coexistence_warning() {
+        let dns = serve_answers(vec![
+            Record {
+                name: "www.test".into(),
+                ty: QueryResponse::Cname("web.test".into()),
+                class: ClassType::IN,
+                ttl: 60,
+                data: encode_dns_name("web.test"),
+            },
+            Record {
+                name: "www.test".into(),
+                ty: QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+                class: ClassType::IN,
+                ttl: 60,
+                data: vec![192, 0, 2, 1],
+            },
+        ]);
+
+        let resolver = Resolver::new().with_servers(&[dns]);
+        let record = resolver.resolve("www.test", QueryType::A).unwrap();
+        assert_eq!(record.ty, QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(
+            resolver.take_warnings(),
+            [ResolveWarning::CnameCoexistence {
+                name: "www.test".into()
+            }]
+        );
+        assert!(resolver.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_a_and_aaaa() {
+        let dns = serve(2, |query| {
+            let (rest, _) = decode_dns_name(&query[12..], query).unwrap();
+            let (ty, data) = match u16::from_be_bytes([rest[0], rest[1]]) {
+                1 => (
+                    QueryResponse::A(Ipv4Addr::new(192, 0, 2, 1)),
+                    vec![192, 0, 2, 1],
+                ),
+                _ => (
+                    QueryResponse::Aaaa(Ipv6Addr::LOCALHOST),
+                    Ipv6Addr::LOCALHOST.octets().to_vec(),
+                ),
+            };
+            let answer = Record {
+                name: "www.test".into(),
+                ty,
+                class: ClassType::IN,
+                ttl: 60,
+                data,
+            };
+            Some(response_bytes(query, &[answer], &[], &[]))
+        });
+        let resolver = Resolver::new().with_servers(&[dns]);
+        assert_eq!(
+            resolver.resolve_a("www.test").unwrap(),
+            [Ipv4Addr::new(192, 0, 2, 1)]
+        );
+        assert_eq!(
+            resolver.resolve_aaaa("www.test").unwrap(),
+            [Ipv6Addr::LOCALHOST]
+        );
+    }
+
+    #[test]
+    fn test_resolve_all_with_options() {
+        let answer = |octet| a_record("www.test", octet);
+        let dns = serve(3, move |query| {
+            let answers = [answer(3), answer(1), answer(3), answer(2)];
+            Some(response_bytes(query, &answers, &[], &[]))
+        });
+        let resolver = Resolver::new().with_servers(&[dns]);
+        let resolve = |sort, dedup| -> Vec<_> {
+            resolver
+                .resolve_all_with_options("www.test", QueryType::A, ResolveOptions { sort, dedup })
+                .unwrap()
+                .iter()
+                .map(Record::data)
+                .collect()
+        };
+
+        assert_eq!(resolve(true, true), ["192.0.2.1", "192.0.2.2", "192.0.2.3"]);
+        assert_eq!(
+            resolve(false, true),
+            ["192.0.2.3", "192.0.2.1", "192.0.2.2"]
+        );
+        assert_eq!(
+            resolve(true, false),
+            ["192.0.2.1", "192.0.2.2", "192.0.2.3", "192.0.2.3"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_expect() {
+        let answer = |octet| a_record("www.test", octet);
+        let dns = serve(2, move |query| {
+            Some(response_bytes(query, &[answer(2), answer(1)], &[], &[]))
+        });
+        let resolver = Resolver::new().with_servers(&[dns]);
+
+        let check = resolver
+            .resolve_expect("www.test", QueryType::A, &["192.0.2.1", "192.0.2.2"])
+            .unwrap();
+        assert!(check.matches());
+
+        let check = resolver
+            .resolve_expect("www.test", QueryType::A, &["192.0.2.1", "192.0.2.3"])
+            .unwrap();
+        assert!(!check.matches());
+        assert_eq!(check.missing, ["192.0.2.3"]);
+        assert_eq!(check.unexpected, ["192.0.2.2"]);
+        assert_eq!(
+            check.to_string(),
+            "missing: 192.0.2.3\nunexpected: 192.0.2.2"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, TcpStream},
+    sync::Arc,
+    time::Duration,
+};
+
+use color_eyre::eyre::Context;
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{ring, WebPkiSupportedAlgorithms},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    StreamOwned,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{read_tcp_message, write_tcp_message, Transport};
+
+/// The port DNS-over-TLS servers listen on ([RFC 7858 section
+/// 3.1](https://datatracker.ietf.org/doc/html/rfc7858#section-3.1))
+pub const DOT_PORT: u16 = 853;
+
+/// Sends queries over DNS-over-TLS, using the same length-prefixed framing as DNS over TCP.  Each
+/// query opens a connection of its own.  By default, the server's certificate must chain to one of
+/// the Mozilla root certificates and be valid for the server name given to `new`.
+pub struct TlsTransport {
+    config: Arc<ClientConfig>,
+    server_name: ServerName<'static>,
+}
+
+impl TlsTransport {
+    /// Verify servers' certificates against `server_name`, which may be a host name or an IP
+    /// address.
+    pub fn new(server_name: &str) -> color_eyre::Result<Self> {
+        let server_name = ServerName::try_from(server_name.to_string())
+            .with_context(|| format!("Invalid TLS server name {server_name:?}"))?;
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .context("Unable to configure TLS")?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Self {
+            config: Arc::new(config),
+            server_name,
+        })
+    }
+
+    /// Accept only the certificate whose DER encoding has the SHA-256 digest `sha256`, instead of
+    /// any certificate a trusted authority issued for the server name.  Useful for servers with
+    /// self-signed certificates, or to guard against misissued ones.
+    pub fn with_pinned_certificate(mut self, sha256: [u8; 32]) -> color_eyre::Result<Self> {
+        let provider = Arc::new(ring::default_provider());
+        let verifier = PinnedCertificate {
+            sha256,
+            algorithms: provider.signature_verification_algorithms,
+        };
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .context("Unable to configure TLS")?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        self.config = Arc::new(config);
+        Ok(self)
+    }
+}
+
+impl Transport for TlsTransport {
+    fn send_and_recv(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+        timeout: Duration,
+        _max_size: usize,
+    ) -> color_eyre::Result<Option<Vec<u8>>> {
+        let stream = match TcpStream::connect_timeout(&server, timeout) {
+            Ok(stream) => stream,
+            Err(e) if e.kind() == ErrorKind::TimedOut => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Unable to connect to {server}")),
+        };
+        stream
+            .set_read_timeout(Some(timeout))
+            .and_then(|()| stream.set_write_timeout(Some(timeout)))
+            .context("Unable to set socket timeout")?;
+        let connection = ClientConnection::new(self.config.clone(), self.server_name.clone())
+            .context("Unable to start TLS session")?;
+        let mut stream = StreamOwned::new(connection, stream);
+        let response =
+            write_tcp_message(&mut stream, request).and_then(|()| read_tcp_message(&mut stream));
+        match response {
+            Ok(response) => Ok(Some(response)),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+            Err(e) => Err(e).context("TLS exchange with server failed"),
+        }
+    }
+}
+
+/// Accepts exactly one certificate, identified by its SHA-256 digest, regardless of who issued it
+/// or which names it's valid for.  Handshake signatures are still checked against it.
+#[derive(Debug)]
+struct PinnedCertificate {
+    sha256: [u8; 32],
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(end_entity.as_ref()).as_slice() == self.sha256 {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "server certificate doesn't match the pinned one".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{QueryType, Resolver};
+    use rustls::{pki_types::PrivateKeyDer, ServerConfig, ServerConnection};
+    use std::{net::TcpListener, thread};
+
+    /// Serve one DNS-over-TLS connection on a local port with a fresh self-signed certificate for
+    /// `localhost`, echoing the query back as an empty response.  Returns the server's address and
+    /// the SHA-256 digest of its certificate.
+    fn serve_once() -> (SocketAddr, [u8; 32]) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert = certified.cert.der().clone();
+        let digest = Sha256::digest(cert.as_ref()).into();
+        let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let connection = ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = StreamOwned::new(connection, stream);
+            // the handshake fails when the client rejects our certificate
+            let Ok(mut response) = read_tcp_message(&mut stream) else {
+                return;
+            };
+            response[2] |= 0x80;
+            let _ = write_tcp_message(&mut stream, &response);
+        });
+        (address, digest)
+    }
+
+    #[test]
+    fn test_pinned_certificate() {
+        let (address, digest) = serve_once();
+        let transport = TlsTransport::new("localhost")
+            .unwrap()
+            .with_pinned_certificate(digest)
+            .unwrap();
+        let response = Resolver::new()
+            .with_transport(transport)
+            .with_retries(0)
+            .query_server(address, "example.com", QueryType::A)
+            .unwrap();
+        assert_eq!(response.answers().count(), 0);
+    }
+
+    #[test]
+    fn test_untrusted_certificate() {
+        let (address, _) = serve_once();
+        let result = Resolver::new()
+            .with_transport(TlsTransport::new("localhost").unwrap())
+            .with_retries(0)
+            .query_server(address, "example.com", QueryType::A);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_wrong_pin() {
+        let (address, _) = serve_once();
+        let transport = TlsTransport::new("localhost")
+            .unwrap()
+            .with_pinned_certificate([0; 32])
+            .unwrap();
+        let result = Resolver::new()
+            .with_transport(transport)
+            .with_retries(0)
+            .query_server(address, "example.com", QueryType::A);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Context;
+
+/// Carries encoded queries to DNS servers and brings back their responses.
+pub trait Transport: Send + Sync {
+    /// Send `request` to `server`, and wait at most `timeout` for the response to it, which may
+    /// be up to `max_size` bytes long.  Returns `None` if no response arrives in time.
+    fn send_and_recv(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+        timeout: Duration,
+        max_size: usize,
+    ) -> color_eyre::Result<Option<Vec<u8>>>;
+
+    /// The local ports queries are currently sent from, for transports that have them.  Useful
+    /// for checking that source ports are being randomized.
+    fn local_ports(&self) -> Vec<u16> {
+        vec![]
+    }
+}
+
+/// Lets a transport be shared, e.g. to inspect a `MockTransport` after handing it to a
+/// `Resolver`.
+impl<T: Transport> Transport for Arc<T> {
+    fn send_and_recv(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+        timeout: Duration,
+        max_size: usize,
+    ) -> color_eyre::Result<Option<Vec<u8>>> {
+        (**self).send_and_recv(server, request, timeout, max_size)
+    }
+
+    fn local_ports(&self) -> Vec<u16> {
+        (**self).local_ports()
+    }
+}
+
+/// Sends queries over UDP, reusing sockets across queries.  Queries made concurrently each get a
+/// socket of their own.  Each socket's port is an ephemeral port picked by the operating system,
+/// which most systems randomize, making responses harder to spoof.  `local_ports` shows the
+/// ports of the sockets that aren't in use.
+#[derive(Default)]
+pub struct UdpTransport {
+    /// sockets not currently in use by a query
+    sockets: Mutex<Vec<UdpSocket>>,
+    /// the address to send queries from, if not left to the system
+    local_address: Option<IpAddr>,
+}
+
+impl UdpTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send queries from `address`, e.g. to pick the interface used on a multi-homed host.
+    /// Queries can then only reach servers of the same address family.
+    pub fn with_local_address(mut self, address: IpAddr) -> Self {
+        self.local_address = Some(address);
+        self
+    }
+
+    /// Take an idle socket of the same address family as `server` to send a query from,
+    /// binding a new one if none are idle.
+    fn take_socket(&self, server: SocketAddr) -> color_eyre::Result<UdpSocket> {
+        let idle = {
+            let mut sockets = self.sockets.lock().unwrap();
+            sockets
+                .iter()
+                .position(|socket| {
+                    socket
+                        .local_addr()
+                        .is_ok_and(|local| local.is_ipv4() == server.is_ipv4())
+                })
+                .map(|i| sockets.swap_remove(i))
+        };
+        if let Some(socket) = idle {
+            if drain(&socket).is_ok() {
+                return Ok(socket);
+            }
+        }
+        let local: IpAddr = match self.local_address {
+            Some(address) => address,
+            None if server.is_ipv4() => Ipv4Addr::UNSPECIFIED.into(),
+            None => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        UdpSocket::bind((local, 0)).context("Unable to bind to socket")
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send_and_recv(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+        timeout: Duration,
+        max_size: usize,
+    ) -> color_eyre::Result<Option<Vec<u8>>> {
+        let connection = self.take_socket(server)?;
+        let response = exchange(&connection, server, request, timeout, max_size);
+        self.sockets.lock().unwrap().push(connection);
+        response
+    }
+
+    fn local_ports(&self) -> Vec<u16> {
+        self.sockets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|socket| socket.local_addr().ok())
+            .map(|address| address.port())
+            .collect()
+    }
+}
+
+/// Send `request` from `connection`, then wait for the response with the same id from `server`,
+/// ignoring any other datagrams (such as late responses to earlier attempts, or forgeries sent
+/// from elsewhere).
+fn exchange(
+    connection: &UdpSocket,
+    server: SocketAddr,
+    request: &[u8],
+    timeout: Duration,
+    max_size: usize,
+) -> color_eyre::Result<Option<Vec<u8>>> {
+    connection
+        .send_to(request, server)
+        .context("Failed to send query to server")?;
+    let deadline = Instant::now() + timeout;
+    let mut buf = vec![0u8; max_size];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        connection
+            .set_read_timeout(Some(remaining))
+            .context("Unable to set socket timeout")?;
+        let size = match connection.recv_from(&mut buf) {
+            Ok((size, peer)) if peer == server => size,
+            Ok(_) => continue,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e).context("No response received"),
+        };
+        if size < 2 || buf[..2] != request[..2] {
+            continue;
+        }
+        buf.truncate(size);
+        return Ok(Some(buf));
+    }
+}
+
+/// Discard any datagrams queued on `socket`, such as duplicate or unsolicited responses to an
+/// earlier query, so they can't be mistaken for the response to the next one.
+fn drain(socket: &UdpSocket) -> std::io::Result<()> {
+    socket.set_nonblocking(true)?;
+    let mut buf = [0u8; 512];
+    let result = loop {
+        match socket.recv_from(&mut buf) {
+            Ok(_) => continue,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    socket.set_nonblocking(false)?;
+    result
+}
+
+/// Sends queries over TCP, opening a connection for each query.  Slower than UDP, but gets
+/// through networks that mangle UDP, and never truncates large responses.
+#[derive(Default)]
+pub struct TcpTransport;
+
+impl TcpTransport {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send_and_recv(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+        timeout: Duration,
+        _max_size: usize,
+    ) -> color_eyre::Result<Option<Vec<u8>>> {
+        let mut stream = match TcpStream::connect_timeout(&server, timeout) {
+            Ok(stream) => stream,
+            Err(e) if e.kind() == ErrorKind::TimedOut => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Unable to connect to {server}")),
+        };
+        stream
+            .set_read_timeout(Some(timeout))
+            .context("Unable to set socket timeout")?;
+        write_tcp_message(&mut stream, request).context("Failed to send query to server")?;
+        match read_tcp_message(&mut stream) {
+            Ok(response) => Ok(Some(response)),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(None),
+            Err(e) => Err(e).context("No response received"),
+        }
+    }
+}
+
+/// Answers queries with canned responses, without touching the network.  Useful for testing
+/// code built on a `Resolver`.
+#[derive(Default)]
+pub struct MockTransport {
+    responses: Mutex<VecDeque<Vec<u8>>>,
+    requests: Mutex<Vec<(SocketAddr, Vec<u8>)>>,
+}
+
+impl MockTransport {
+    /// Answer each query with the next of `responses`, in order, with its id replaced by the
+    /// query's.  Once they run out, queries time out.
+    pub fn new(responses: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into_iter().collect()),
+            requests: Mutex::new(vec![]),
+        }
+    }
+
+    /// The queries sent so far, and the servers they were sent to
+    pub fn requests(&self) -> Vec<(SocketAddr, Vec<u8>)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send_and_recv(
+        &self,
+        server: SocketAddr,
+        request: &[u8],
+        _timeout: Duration,
+        _max_size: usize,
+    ) -> color_eyre::Result<Option<Vec<u8>>> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push((server, request.to_vec()));
+        let response = self.responses.lock().unwrap().pop_front();
+        Ok(response.map(|mut response| {
+            if response.len() >= 2 && request.len() >= 2 {
+                response[..2].copy_from_slice(&request[..2]);
+            }
+            response
+        }))
+    }
+}
+
+/// Write `message` to a TCP `stream`, prefixed with its length as DNS over TCP requires ([RFC
+/// 1035 section 4.2.2](https://datatracker.ietf.org/doc/html/rfc1035#section-4.2.2))
+pub(crate) fn write_tcp_message(stream: &mut impl Write, message: &[u8]) -> std::io::Result<()> {
+    let len = u16::try_from(message.len())
+        .map_err(|_| std::io::Error::new(ErrorKind::InvalidInput, "message is too long"))?;
+    let mut framed = Vec::with_capacity(message.len() + 2);
+    framed.extend_from_slice(&len.to_be_bytes());
+    framed.extend_from_slice(message);
+    stream.write_all(&framed)
+}
+
+/// Read one length-prefixed message from a TCP `stream`
+pub(crate) fn read_tcp_message(stream: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len)?;
+    let mut message = vec![0u8; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}